@@ -7,6 +7,12 @@
 
 
 
+use core::convert::Infallible;
+
+use embassy_time::{
+    Duration, Timer,
+};
+
 use embedded_hal::{
     digital::v2::{
         OutputPin,
@@ -22,13 +28,19 @@ use embedded_hal_async::{
     },
 };
 
-use super::common::{
-    BlockRegister, Command, Register, Status,
+use super::{
+    beken::Beken,
+    common::{
+        AckPolicy, BlockRegister, ChipVariant, Command, IrqEvents, Register, Status,
+    },
+    frontend::{
+        FrontEnd, NoFrontEnd,
+    },
 };
 
 
 
-pub struct BaseDriver<SPI: SpiBus + SpiBusWrite, CS: OutputPin, CE: OutputPin, IRQ: Wait> {
+pub struct BaseDriver<SPI: SpiBus + SpiBusWrite, CS: OutputPin, CE: OutputPin, IRQ: Wait, FE: FrontEnd = NoFrontEnd> {
     /// The SPI device bus.
     spi: SPI,
 
@@ -40,30 +52,100 @@ pub struct BaseDriver<SPI: SpiBus + SpiBusWrite, CS: OutputPin, CE: OutputPin, I
 
     /// The IRQ pin.
     irq: IRQ,
+
+    /// The external front end.
+    fe: FE,
+
+    /// `true` if the device was last configured as a receiver.
+    prx: bool,
+
+    /// Interval to poll STATUS at instead of waiting for the IRQ pin.
+    poll: Option<Duration>,
 }
 
 impl<SPI: SpiBus + SpiBusWrite, CS: OutputPin, CE: OutputPin, IRQ: Wait> BaseDriver<SPI, CS, CE, IRQ> {
+    /// Takes ownership of the resources and creates the base driver.
+    pub fn new(spi: SPI, cs: CS, ce: CE, irq: IRQ) -> Self {
+        Self::with_frontend(spi, cs, ce, irq, NoFrontEnd)
+    }
+}
+
+impl<SPI: SpiBus + SpiBusWrite, CS: OutputPin, CE: OutputPin> BaseDriver<SPI, CS, CE, NoIrq> {
+    /// Takes ownership of the resources of a board without the IRQ line wired and creates the
+    /// base driver, which polls STATUS at the given interval.
+    pub fn without_irq(spi: SPI, cs: CS, ce: CE, interval: Duration) -> Self {
+        Self::new(spi, cs, ce, NoIrq).polling(interval)
+    }
+}
+
+impl<SPI: SpiBus + SpiBusWrite, CS: OutputPin, CE: OutputPin, IRQ: Wait, FE: FrontEnd> BaseDriver<SPI, CS, CE, IRQ, FE> {
     /// Write command.
     const WRITECMD: u8 = 0b00100000;
 
-    /// Takes ownership of the resources and creates the base driver.
-    pub fn new(spi: SPI, cs: CS, ce: CE, irq: IRQ) -> Self {
-        Self { spi, cs, ce, irq }
+    /// Key of the ACTIVATE command that switches the register bank of BK242x devices.
+    pub const BANKSWITCH: u8 = 0x53;
+
+    /// Takes ownership of the resources and an external front end and creates the base driver.
+    pub fn with_frontend(spi: SPI, cs: CS, ce: CE, irq: IRQ, mut fe: FE) -> Self {
+        fe.off();
+
+        Self { spi, cs, ce, irq, fe, prx: false, poll: None }
+    }
+
+    /// Polls STATUS at the given interval instead of waiting for the IRQ pin.
+    /// Masked events are still reported when polling.
+    pub fn polling(mut self, interval: Duration) -> Self {
+        self.poll = Some( interval );
+        self
     }
 
     /// Enables the CE signal.
+    /// The front end switches to the role of the device first.
     pub(crate) fn enable(&mut self) {
+        if self.prx {
+            self.fe.rx();
+        } else {
+            self.fe.tx();
+        }
+
         self.ce.set_high();
     }
 
     /// Disables the CE signal.
+    /// The front end switches off afterwards.
     pub(crate) fn disable(&mut self) {
         self.ce.set_low();
+
+        self.fe.off();
     }
 
-    /// Waits for the IRQ signal.
-    pub(crate) async fn wait(&mut self) {
-        self.irq.wait_for_low().await;
+    /// Waits for an interrupt and returns its events.
+    /// Not cancel safe: use `pending` and `events` to race the wait with other futures.
+    pub async fn wait(&mut self) -> Result<IrqEvents, SPI::Error> {
+        loop {
+            self.pending().await;
+
+            let events = self.events().await?;
+
+            if !events.is_empty() {
+                return Ok( events );
+            }
+        }
+    }
+
+    /// Waits until an interrupt may be pending: the IRQ pin is low, or the poll interval elapsed
+    /// when polling without IRQ pin.
+    /// Only waits on the pin or a timer, so it can be dropped at any point.
+    pub async fn pending(&mut self) {
+        match self.poll {
+            Some(interval) => Timer::after(interval).await,
+            _ => { self.irq.wait_for_low().await; },
+        }
+    }
+
+    /// Reads and clears the events flagged in STATUS in a single transaction.
+    pub async fn events(&mut self) -> Result<IrqEvents, SPI::Error> {
+        Ok( IrqEvents::from_status( self.writereg(Register::Status, IrqEvents::ALL.bits()).await? ) )
     }
 
     /// Sends a command to the device.
@@ -86,6 +168,72 @@ impl<SPI: SpiBus + SpiBusWrite, CS: OutputPin, CE: OutputPin, IRQ: Wait> BaseDri
         Ok( Status(read[0]) )
     }
 
+    /// Sends the ACTIVATE command with the given key.
+    pub(crate) async fn activate(&mut self, key: u8) -> Result<Status, SPI::Error> {
+        // Command to send.
+        let write = [Command::Activate as u8, key];
+
+        // Input buffer.
+        let mut read = [0, 0];
+
+        // Set CS low.
+        self.cs.set_low();
+
+        // Perform transfer.
+        self.spi.transfer(&mut read, &write).await?;
+
+        // Set CS high.
+        self.cs.set_high();
+
+        Ok( Status(read[0]) )
+    }
+
+    /// Detects the variant of the device and unlocks its features if needed.
+    /// BK242x devices are recognized first by their chip ID in bank 1, as they also keep bit 0
+    /// of RF_SETUP (LNA_HCURR). Among the others, RF_DR_LOW is reserved in the original part, and
+    /// only the Si24R1 keeps bit 0 of RF_SETUP as part of its PA control.
+    pub async fn detect(&mut self) -> Result<ChipVariant, SPI::Error> {
+        if Beken::present(self).await? {
+            return Ok( ChipVariant::Bk242x );
+        }
+
+        // Probe RF_SETUP and restore it.
+        let (_, rfsetup) = self.readreg(Register::RFSetup).await?;
+
+        self.writereg(Register::RFSetup, rfsetup | (1 << 5) | 1).await?;
+        let (_, probe) = self.readreg(Register::RFSetup).await?;
+        self.writereg(Register::RFSetup, rfsetup).await?;
+
+        let variant = match probe {
+            p if (p & (1 << 5)) == 0 => ChipVariant::Nrf24l01,
+            p if (p & 1) != 0 => ChipVariant::Si24r1,
+            _ => ChipVariant::Nrf24l01Plus,
+        };
+
+        if variant == ChipVariant::Nrf24l01 {
+            self.unlock().await?;
+        }
+
+        Ok( variant )
+    }
+
+    /// Unlocks FEATURE and DYNPD in devices that ignore writes to them until activated.
+    /// ACTIVATE toggles the features, so it is only sent if they are locked.
+    pub async fn unlock(&mut self) -> Result<(), SPI::Error> {
+        let (_, feature) = self.readreg(Register::Feature).await?;
+
+        self.writereg(Register::Feature, feature ^ 1).await?;
+        let (_, probe) = self.readreg(Register::Feature).await?;
+
+        if probe == feature {
+            self.activate(ChipVariant::ACTIVATE).await?;
+        } else {
+            self.writereg(Register::Feature, feature).await?;
+        }
+
+        Ok(())
+    }
+
     /// Reads the given register.
     pub async fn readreg(&mut self, r: Register) -> Result<(Status, u8), SPI::Error> {
         // Command to send.
@@ -107,7 +255,16 @@ impl<SPI: SpiBus + SpiBusWrite, CS: OutputPin, CE: OutputPin, IRQ: Wait> BaseDri
     }
 
     /// Writes to the register the given value.
-    pub(crate) async fn writereg(&mut self, r: Register, v: u8) -> Result<Status, SPI::Error> {
+    /// Writes to CONFIG keep track of the role of the device, and switch the front end off on power down.
+    pub async fn writereg(&mut self, r: Register, v: u8) -> Result<Status, SPI::Error> {
+        if r == Register::Config {
+            self.prx = (v & 1) != 0;
+
+            if (v & (1 << 1)) == 0 {
+                self.fe.off();
+            }
+        }
+
         // Command to send.
         let write = [(r as u8) | Self::WRITECMD, v];
 
@@ -129,7 +286,7 @@ impl<SPI: SpiBus + SpiBusWrite, CS: OutputPin, CE: OutputPin, IRQ: Wait> BaseDri
     /// Reads the given register block.
     pub async fn readblock(&mut self, r: BlockRegister) -> Result<(Status, [u8; 5]), SPI::Error> {
         // Command to send.
-        let write = [(r as u8) | Self::WRITECMD, 0, 0, 0, 0, 0];
+        let write = [r as u8, 0, 0, 0, 0, 0];
 
         // Input buffer.
         let mut read = [0; 6];
@@ -154,7 +311,7 @@ impl<SPI: SpiBus + SpiBusWrite, CS: OutputPin, CE: OutputPin, IRQ: Wait> BaseDri
     }
 
     /// Writes to the register block the given data.
-    pub(crate) async fn writeblock<'a>(&mut self, r: BlockRegister, data: &'a [u8; 5]) -> Result<Status, SPI::Error> {
+    pub async fn writeblock<'a>(&mut self, r: BlockRegister, data: &'a [u8; 5]) -> Result<Status, SPI::Error> {
         // Command to send.
         let write = [
             (r as u8) | Self::WRITECMD,
@@ -197,12 +354,106 @@ impl<SPI: SpiBus + SpiBusWrite, CS: OutputPin, CE: OutputPin, IRQ: Wait> BaseDri
         let mut words = [0; 33];
         words[0] = Command::RXPayload as u8;
 
+        // Set CS low.
+        self.cs.set_low();
+
         // Perform the transfer.
         self.spi.transfer_in_place(&mut words).await?;
 
+        // Set CS high.
+        self.cs.set_high();
+
         Ok( Some( (len, words) ) )
     }
 
+    /// Writes a TX payload with the given acknowledge policy.
+    /// Payloads longer than 32 bytes are truncated.
+    pub(crate) async fn txpayload(&mut self, data: &[u8], ack: AckPolicy) -> Result<Status, SPI::Error> {
+        // Length of the payload.
+        let len = data.len().min(32);
+
+        // Builds the output buffer.
+        let mut words = [0; 33];
+        words[0] = ack.command() as u8;
+        words[1..=len].copy_from_slice(&data[..len]);
+
+        // Set CS low.
+        self.cs.set_low();
+
+        // Perform the transfer.
+        self.spi.transfer_in_place(&mut words[..=len]).await?;
+
+        // Set CS high.
+        self.cs.set_high();
+
+        Ok( Status(words[0]) )
+    }
+
+    /// Writes raw data to the register at the given address.
+    /// Used for the multi-byte registers of BK242x devices, at most 16 bytes.
+    pub async fn writeraw(&mut self, address: u8, data: &[u8]) -> Result<Status, SPI::Error> {
+        // Length of the data.
+        let len = data.len().min(16);
+
+        // Command to send.
+        let mut write = [0; 17];
+        write[0] = (address & 0x1F) | Self::WRITECMD;
+        write[1..=len].copy_from_slice(&data[..len]);
+
+        // Input buffer.
+        let mut read = [0; 17];
+
+        // Set CS low.
+        self.cs.set_low();
+
+        // Perform the transfer.
+        self.spi.transfer(&mut read[..=len], &write[..=len]).await?;
+
+        // Set CS high.
+        self.cs.set_high();
+
+        Ok( Status(read[0]) )
+    }
+
+    /// Reads raw data from the register at the given address.
+    /// Used for the multi-byte registers of BK242x devices, at most 16 bytes.
+    pub async fn readraw(&mut self, address: u8, data: &mut [u8]) -> Result<Status, SPI::Error> {
+        // Length of the data.
+        let len = data.len().min(16);
+
+        // Command to send.
+        let mut write = [0; 17];
+        write[0] = address & 0x1F;
+
+        // Input buffer.
+        let mut read = [0; 17];
+
+        // Set CS low.
+        self.cs.set_low();
+
+        // Perform the transfer.
+        self.spi.transfer(&mut read[..=len], &write[..=len]).await?;
+
+        // Set CS high.
+        self.cs.set_high();
+
+        data[..len].copy_from_slice(&read[1..=len]);
+
+        Ok( Status(read[0]) )
+    }
+
+    /// Selects the register bank of BK242x devices.
+    /// ACTIVATE with key `0x53` toggles the bank, so it is only sent if the bank differs.
+    pub async fn bank(&mut self, bank: u8) -> Result<Status, SPI::Error> {
+        let status = self.command(Command::Nop).await?;
+
+        if status.bank() == (bank & 1) {
+            return Ok( status );
+        }
+
+        self.activate(Self::BANKSWITCH).await
+    }
+
     /// Reads the RX payload width.
     async fn rxpldwidth(&mut self) -> Result<u8, SPI::Error> {
         // Write command.
@@ -223,3 +474,35 @@ impl<SPI: SpiBus + SpiBusWrite, CS: OutputPin, CE: OutputPin, IRQ: Wait> BaseDri
         Ok( read[1] )
     }
 }
+
+
+
+/// IRQ pin of boards without the IRQ line wired.
+/// Never signals, so the driver must poll STATUS.
+pub struct NoIrq;
+
+impl embedded_hal_1::digital::ErrorType for NoIrq {
+    type Error = Infallible;
+}
+
+impl Wait for NoIrq {
+    async fn wait_for_high(&mut self) -> Result<(), Self::Error> {
+        core::future::pending().await
+    }
+
+    async fn wait_for_low(&mut self) -> Result<(), Self::Error> {
+        core::future::pending().await
+    }
+
+    async fn wait_for_rising_edge(&mut self) -> Result<(), Self::Error> {
+        core::future::pending().await
+    }
+
+    async fn wait_for_falling_edge(&mut self) -> Result<(), Self::Error> {
+        core::future::pending().await
+    }
+
+    async fn wait_for_any_edge(&mut self) -> Result<(), Self::Error> {
+        core::future::pending().await
+    }
+}
@@ -0,0 +1,119 @@
+//! Support of the Beken BK2423 (RFM70) and BK2425 (RFM73) devices.
+//! These are register compatible with the NRF24L01+ in bank 0, but the analog front end in
+//! bank 1 must be initialized with the vendor tables after every power on reset. Once
+//! initialized, the device works with `Receiver` unchanged.
+
+
+
+use embedded_hal::{
+    digital::v2::{
+        OutputPin,
+    },
+};
+
+use embedded_hal_async::{
+    digital::{
+        Wait,
+    },
+    spi::{
+        SpiBus, SpiBusWrite,
+    },
+};
+
+use crate::{
+    base::BaseDriver,
+    common::Command,
+    frontend::FrontEnd,
+};
+
+
+
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub enum Beken {
+    /// BK2423, found in RFM70 modules.
+    Bk2423,
+
+    /// BK2425, found in RFM73 modules.
+    Bk2425,
+}
+
+impl Beken {
+    /// Chip ID read from register 8 of bank 1, LSB first.
+    pub const CHIPID: [u8; 4] = [0x63, 0x00, 0x00, 0x00];
+
+    /// Value of register 14 of bank 1, shared by both devices.
+    pub const REG14: [u8; 11] = [0x41, 0x20, 0x08, 0x04, 0x81, 0x20, 0xCF, 0xF7, 0xFE, 0xFF, 0xFF];
+
+    /// Returns the values of registers 0 to 13 of bank 1 from the vendor sample code.
+    pub const fn bank1(&self) -> [u32; 14] {
+        match self {
+            Beken::Bk2423 => [
+                0xE2014B40, 0x00004BC0, 0x028CFCD0, 0x41390099,
+                0x0B869ED9, 0xA67F0624, 0x00000000, 0x00000000,
+                0x00000000, 0x00000000, 0x00000000, 0x00000000,
+                0x00127300, 0x36B48000,
+            ],
+
+            Beken::Bk2425 => [
+                0xE2014B40, 0x00004BC0, 0x028CFCD0, 0x41390099,
+                0x1B8296D9, 0xA67F0224, 0x00000000, 0x00000000,
+                0x00000000, 0x00000000, 0x00000000, 0x00000000,
+                0x00127300, 0x36B48000,
+            ],
+        }
+    }
+
+    /// Returns `true` if the device is a BK242x, reading the chip ID from bank 1.
+    /// Other devices ignore the bank switch, so the bank bit of STATUS must flip before the ID
+    /// is trusted. The device is left in bank 0.
+    pub async fn present<SPI: SpiBus + SpiBusWrite, CS: OutputPin, CE: OutputPin, IRQ: Wait, FE: FrontEnd>(base: &mut BaseDriver<SPI, CS, CE, IRQ, FE>) -> Result<bool, SPI::Error> {
+        base.bank(1).await?;
+
+        if base.command( Command::Nop ).await?.bank() != 1 {
+            return Ok( false );
+        }
+
+        let mut id = [0; 4];
+
+        base.readraw(8, &mut id).await?;
+        base.bank(0).await?;
+
+        Ok( id == Self::CHIPID )
+    }
+
+    /// Unlocks the features in bank 0 and writes the vendor tables into bank 1.
+    /// The device is left in bank 0.
+    pub async fn init<SPI: SpiBus + SpiBusWrite, CS: OutputPin, CE: OutputPin, IRQ: Wait, FE: FrontEnd>(&self, base: &mut BaseDriver<SPI, CS, CE, IRQ, FE>) -> Result<(), SPI::Error> {
+        base.bank(0).await?;
+        base.unlock().await?;
+
+        base.bank(1).await?;
+
+        let table = self.bank1();
+
+        // Registers 0 to 8 are written LSB first and registers 9 to 13 MSB first.
+        for (address, value) in table.iter().enumerate() {
+            let bytes = match address {
+                0..=8 => value.to_le_bytes(),
+                _ => value.to_be_bytes(),
+            };
+
+            base.writeraw(address as u8, &bytes).await?;
+        }
+
+        base.writeraw(14, &Self::REG14).await?;
+
+        // Toggle the calibration bits of register 4, as the vendor sample code does.
+        let mut reg4 = table[4].to_le_bytes();
+
+        reg4[0] |= 0x06;
+        base.writeraw(4, &reg4).await?;
+
+        reg4[0] &= 0xF9;
+        base.writeraw(4, &reg4).await?;
+
+        base.bank(0).await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,615 @@
+//! Command line tool to inspect and drive a NRF24L01(+) device.
+//! Runs against a device wired to spidev and the GPIO character device, or against the
+//! software simulator with `--sim`.
+
+
+
+use std::{
+    fmt::Debug,
+    fs::File,
+    process::ExitCode,
+    time::{
+        SystemTime, UNIX_EPOCH,
+    },
+};
+
+use clap::{
+    Args, Parser, Subcommand, ValueEnum,
+};
+
+use embassy_time::{
+    Duration, Timer,
+};
+
+use embedded_hal::{
+    digital::v2::{
+        OutputPin,
+    },
+};
+
+use embedded_hal_async::{
+    digital::{
+        Wait,
+    },
+    spi::{
+        SpiBus, SpiBusWrite,
+    },
+};
+
+use nrf24::{
+    base::BaseDriver,
+    capture::Capture,
+    common::{
+        AckPolicy, AddressWidth, BlockRegister, Channel, ChipVariant, CRCBytes, DataRate, Error, Gain, Register,
+    },
+    linux::{
+        self, KernelCs, LinuxIrq, LinuxSpi,
+    },
+    rx::{
+        Config, ConfigBuilder, Pipe, PipeAddress, Receiver,
+    },
+    sim::Simulator,
+};
+
+
+
+/// Single byte registers, in address order.
+const REGISTERS: [(&str, Register); 23] = [
+    ("CONFIG",      Register::Config),
+    ("EN_AA",       Register::AutoAck),
+    ("EN_RXADDR",   Register::RXEnable),
+    ("SETUP_AW",    Register::AddressWidth),
+    ("SETUP_RETR",  Register::Retries),
+    ("RF_CH",       Register::RFChannel),
+    ("RF_SETUP",    Register::RFSetup),
+    ("STATUS",      Register::Status),
+    ("OBSERVE_TX",  Register::Observe),
+    ("RPD",         Register::Detector),
+    ("RX_ADDR_P2",  Register::RX2Address),
+    ("RX_ADDR_P3",  Register::RX3Address),
+    ("RX_ADDR_P4",  Register::RX4Address),
+    ("RX_ADDR_P5",  Register::RX5Address),
+    ("RX_PW_P0",    Register::RX0Width),
+    ("RX_PW_P1",    Register::RX1Width),
+    ("RX_PW_P2",    Register::RX2Width),
+    ("RX_PW_P3",    Register::RX3Width),
+    ("RX_PW_P4",    Register::RX4Width),
+    ("RX_PW_P5",    Register::RX5Width),
+    ("FIFO_STATUS", Register::FifoStatus),
+    ("DYNPD",       Register::DynamicPayload),
+    ("FEATURE",     Register::Feature),
+];
+
+/// Address registers.
+const BLOCKS: [(&str, BlockRegister); 3] = [
+    ("RX_ADDR_P0", BlockRegister::RX0Address),
+    ("RX_ADDR_P1", BlockRegister::RX1Address),
+    ("TX_ADDR",    BlockRegister::TXAddress),
+];
+
+
+
+#[derive(Parser)]
+#[command(name = "nrf24-cli", version, about = "Inspect and drive a NRF24L01(+) device")]
+struct Cli {
+    /// Use the software simulator instead of a real device.
+    #[arg(long, global = true)]
+    sim: bool,
+
+    /// SPI device.
+    #[arg(long, global = true, default_value = "/dev/spidev0.0")]
+    spi: String,
+
+    /// SPI clock in Hz.
+    #[arg(long, global = true, default_value_t = 8_000_000)]
+    speed: u32,
+
+    /// GPIO chip of the CE and IRQ lines.
+    #[arg(long, global = true, default_value = "/dev/gpiochip0")]
+    gpiochip: String,
+
+    /// GPIO line of the CE pin.
+    #[arg(long, global = true, default_value_t = 25)]
+    ce: u32,
+
+    /// GPIO line of the IRQ pin.
+    #[arg(long, global = true, default_value_t = 24)]
+    irq: u32,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Reads and decodes every register.
+    Dump,
+
+    /// Listens on the given pipes and prints the payloads received.
+    Listen {
+        #[command(flatten)]
+        radio: Radio,
+
+        /// Pipe and address as `PIPE=HEX`, MSB first. Pipes 1 to 5 share the MSBs and also take a single byte.
+        /// Defaults to pipe 0 at E7E7E7E7E7 and pipe 1 at C2C2C2C2C2.
+        #[arg(long = "pipe", value_parser = pipe)]
+        pipes: Vec<(u8, Hex)>,
+
+        /// Prints one JSON object per payload.
+        #[arg(long)]
+        json: bool,
+
+        /// Also writes the payloads into a pcapng file.
+        #[arg(long)]
+        pcap: Option<String>,
+
+        /// Stops after the given number of payloads.
+        #[arg(long)]
+        count: Option<usize>,
+
+        /// Injects a payload as `PIPE=HEX` into the simulator.
+        #[arg(long, value_parser = pipe)]
+        inject: Vec<(u8, Hex)>,
+    },
+
+    /// Sends a payload and reports if it was acknowledged.
+    Send {
+        #[command(flatten)]
+        radio: Radio,
+
+        /// Destination address in hex, MSB first.
+        #[arg(long, value_parser = hex, default_value = "E7E7E7E7E7")]
+        address: Hex,
+
+        /// Payload in hex.
+        #[arg(value_parser = hex)]
+        data: Hex,
+
+        /// Also writes the payload into a pcapng file.
+        #[arg(long)]
+        pcap: Option<String>,
+    },
+
+    /// Sweeps the channels and prints the carriers detected in each one.
+    Scan {
+        #[command(flatten)]
+        radio: Radio,
+
+        /// Number of sweeps.
+        #[arg(long, default_value_t = 32)]
+        sweeps: usize,
+
+        /// Places a carrier in the given channel of the simulator.
+        #[arg(long)]
+        carrier: Vec<u8>,
+    },
+
+    /// Writes a register, given by name or address.
+    Set {
+        /// Register name (for example `RF_CH`) or address.
+        register: String,
+
+        /// Value in decimal or `0x` hex. Address registers take 5 bytes of hex, MSB first.
+        value: String,
+    },
+}
+
+#[derive(Args)]
+struct Radio {
+    /// RF channel.
+    #[arg(long, default_value_t = 76, value_parser = clap::value_parser!(u8).range(0..=125))]
+    channel: u8,
+
+    /// Data rate.
+    #[arg(long, value_enum, default_value_t = Rate::Mid)]
+    rate: Rate,
+
+    /// Gain of the PA.
+    #[arg(long, value_enum, default_value_t = Level::Max)]
+    gain: Level,
+}
+
+impl Radio {
+    /// Builds the configuration of the device with the given pipes.
+    /// Addresses are given MSB first.
+    fn config(&self, pipes: &[(u8, Hex)]) -> Result<Config, String> {
+        let mut builder = ConfigBuilder::new();
+
+        if pipes.is_empty() {
+            builder = builder
+                .pipe(0, Pipe::dynamic(PipeAddress::Full([Config::PRIMARY; 5]), true))
+                .pipe(1, Pipe::dynamic(PipeAddress::Full([Config::PREFIX; 5]), true));
+        }
+
+        for (pipe, Hex(bytes)) in pipes {
+            let address = match (pipe, bytes.len()) {
+                (0..=5, 5) => PipeAddress::Full(lsbfirst(bytes)?),
+                (1..=5, 1) => PipeAddress::Lsb(bytes[0]),
+                (0, _) => return Err( String::from("pipe 0 takes a 5 byte address") ),
+                (1..=5, _) => return Err( format!("pipe {} takes a 5 byte address or a single byte", pipe) ),
+                _ => return Err( format!("invalid pipe {}", pipe) ),
+            };
+
+            builder = builder.pipe(*pipe as usize, Pipe::dynamic(address, true));
+        }
+
+        let channel = Channel::new(self.channel).ok_or(format!("channel {} is over {}", self.channel, Channel::MAX))?;
+
+        let builder = builder
+            .channel(channel)
+            .datarate(self.rate.into())
+            .gain(self.gain.into());
+
+        builder.build().map_err(|errors| errors.iter().map(|e| format!("{:?}: {}", e, e.message())).collect::<Vec<_>>().join(", "))
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Rate {
+    #[value(name = "250k")]
+    Low,
+
+    #[value(name = "1m")]
+    Mid,
+
+    #[value(name = "2m")]
+    High,
+}
+
+impl From<Rate> for DataRate {
+    fn from(rate: Rate) -> DataRate {
+        match rate {
+            Rate::Low => DataRate::Low,
+            Rate::Mid => DataRate::High,
+            Rate::High => DataRate::Max,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Level {
+    Min,
+    Low,
+    High,
+    Max,
+}
+
+impl From<Level> for Gain {
+    fn from(level: Level) -> Gain {
+        match level {
+            Level::Min => Gain::Min,
+            Level::Low => Gain::Low,
+            Level::High => Gain::High,
+            Level::Max => Gain::Max,
+        }
+    }
+}
+
+
+
+/// Bytes given in hex.
+#[derive(Clone)]
+struct Hex(Vec<u8>);
+
+/// Parses a string of hex bytes.
+fn hex(s: &str) -> Result<Hex, String> {
+    let s = s.trim_start_matches("0x");
+
+    if s.is_empty() || !s.len().is_multiple_of(2) {
+        return Err( "expected an even number of hex digits".into() );
+    }
+
+    (0..s.len()).step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i+2], 16).map_err(|e| e.to_string()))
+        .collect::<Result<Vec<u8>, String>>()
+        .map(Hex)
+}
+
+/// Parses a `PIPE=HEX` pair.
+fn pipe(s: &str) -> Result<(u8, Hex), String> {
+    let (pipe, data) = s.split_once('=').ok_or("expected PIPE=HEX")?;
+
+    let pipe = pipe.parse::<u8>().map_err(|e| e.to_string())?;
+
+    if pipe > 5 {
+        return Err( "pipes go from 0 to 5".into() );
+    }
+
+    Ok( (pipe, hex(data)?) )
+}
+
+/// Parses a byte in decimal or `0x` hex.
+fn byte(s: &str) -> Result<u8, String> {
+    match s.strip_prefix("0x") {
+        Some(h) => u8::from_str_radix(h, 16),
+        _ => s.parse::<u8>(),
+    }.map_err(|e| format!("invalid byte {}: {}", s, e))
+}
+
+/// Converts an address written MSB first into the order of the device.
+fn lsbfirst(bytes: &[u8]) -> Result<[u8; 5], String> {
+    let mut address = <[u8; 5]>::try_from(bytes).map_err(|_| "expected a 5 byte address".to_string())?;
+    address.reverse();
+
+    Ok( address )
+}
+
+/// Creates a capture file.
+fn create(path: String) -> Result<File, String> {
+    File::create(&path).map_err(|e| format!("{}: {}", path, e))
+}
+
+/// Formats bytes in hex.
+fn format(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02X}", b)).collect()
+}
+
+
+
+/// Decodes the fields of a register of the given variant.
+fn decode(register: Register, v: u8, chip: ChipVariant) -> String {
+    let bit = |n: u8| (v >> n) & 1;
+
+    match register {
+        Register::Config => format!("MASK_RX_DR={} MASK_TX_DS={} MASK_MAX_RT={} EN_CRC={} CRCO={} PWR_UP={} PRIM_RX={}", bit(6), bit(5), bit(4), bit(3), bit(2), bit(1), bit(0)),
+
+        Register::AutoAck | Register::RXEnable | Register::DynamicPayload => format!("pipes={:06b}", v & 0x3F),
+
+        Register::AddressWidth => match v & 0b11 {
+            0 => "AW=2 bytes (undocumented)".into(),
+            aw => format!("AW={} bytes", aw + 2),
+        },
+
+        Register::Retries => format!("ARD={}us ARC={}", ((v >> 4) as u32 + 1) * 250, v & 0xF),
+
+        Register::RFChannel => format!("RF_CH={} ({} MHz)", v & 0x7F, 2400 + (v & 0x7F) as u32),
+
+        Register::RFSetup => {
+            let rate = match (bit(5), bit(3)) {
+                (1, _) => "250kbps",
+                (0, 1) => "2Mbps",
+                _ => "1Mbps",
+            };
+
+            let gain = Gain::ALL[((v >> 1) & 0b11) as usize];
+
+            format!("CONT_WAVE={} RF_DR={} RF_PWR={}dBm", bit(7), rate, chip.dbm(gain))
+        },
+
+        Register::Status => format!("RX_DR={} TX_DS={} MAX_RT={} RX_P_NO={} TX_FULL={}", bit(6), bit(5), bit(4), (v >> 1) & 0b111, bit(0)),
+
+        Register::Observe => format!("PLOS_CNT={} ARC_CNT={}", v >> 4, v & 0xF),
+
+        Register::Detector => format!("RPD={}", bit(0)),
+
+        Register::FifoStatus => format!("TX_REUSE={} TX_FULL={} TX_EMPTY={} RX_FULL={} RX_EMPTY={}", bit(6), bit(5), bit(4), bit(1), bit(0)),
+
+        Register::Feature => format!("EN_DPL={} EN_ACK_PAY={} EN_DYN_ACK={}", bit(2), bit(1), bit(0)),
+
+        _ => format!("{}", v),
+    }
+}
+
+/// Reads and prints every register.
+async fn dump<SPI: SpiBus + SpiBusWrite, CS: OutputPin, CE: OutputPin, IRQ: Wait>(base: &mut BaseDriver<SPI, CS, CE, IRQ>) -> Result<(), String> where SPI::Error: Debug {
+    let variant = base.detect().await.map_err(|e| format!("{:?}", e))?;
+
+    let chip = match variant {
+        ChipVariant::Nrf24l01 => "NRF24L01",
+        ChipVariant::Nrf24l01Plus => "NRF24L01+",
+        ChipVariant::Si24r1 => "Si24R1",
+        ChipVariant::Bk242x => "BK242x",
+    };
+
+    println!("chip {}", chip);
+
+    for (name, register) in REGISTERS {
+        let (_, v) = base.readreg(register).await.map_err(|e| format!("{:?}", e))?;
+
+        println!("{:02X} {:<12} {:02X}  {}", register as u8, name, v, decode(register, v, variant));
+    }
+
+    for (name, register) in BLOCKS {
+        let (_, mut v) = base.readblock(register).await.map_err(|e| format!("{:?}", e))?;
+        v.reverse();
+
+        println!("{:02X} {:<12} {}", register as u8, name, format(&v));
+    }
+
+    Ok(())
+}
+
+/// Writes a register given by name or address.
+async fn set<SPI: SpiBus + SpiBusWrite, CS: OutputPin, CE: OutputPin, IRQ: Wait>(base: &mut BaseDriver<SPI, CS, CE, IRQ>, register: &str, value: &str) -> Result<(), String> where SPI::Error: Debug {
+    // Address of the register, if given as a number.
+    let address = byte(register).ok();
+
+    if let Some((_, block)) = BLOCKS.iter().find(|(n, r)| n.eq_ignore_ascii_case(register) || (Some(*r as u8) == address)) {
+        let data = lsbfirst(&hex(value)?.0)?;
+
+        base.writeblock(*block, &data).await.map_err(|e| format!("{:?}", e))?;
+
+        return Ok(());
+    }
+
+    match REGISTERS.iter().find(|(n, r)| n.eq_ignore_ascii_case(register) || (Some(*r as u8) == address)) {
+        Some((_, register)) => {
+            base.writereg(*register, byte(value)?).await.map_err(|e| format!("{:?}", e))?;
+
+            Ok(())
+        },
+
+        _ => Err( format!("unknown register {}", register) ),
+    }
+}
+
+/// Listens and prints the payloads received.
+async fn listen<SPI: SpiBus + SpiBusWrite, CS: OutputPin, CE: OutputPin, IRQ: Wait>(rx: &mut Receiver<SPI, CS, CE, IRQ>, json: bool, mut pcap: Option<File>, count: Option<usize>, sim: Option<(&Simulator, &[(u8, Hex)])>) -> Result<(), String> where SPI::Error: Debug {
+    rx.listen().await.map_err(|e| format!("{:?}", e))?;
+
+    // In the simulator, stop once the injected payloads are received.
+    let count = match sim {
+        Some((sim, inject)) => {
+            for (pipe, Hex(data)) in inject {
+                if !sim.inject(*pipe, data) {
+                    eprintln!("payload for pipe {} dropped", pipe);
+                }
+            }
+
+            Some( count.unwrap_or(inject.len()) )
+        },
+
+        _ => count,
+    };
+
+    let mut received = 0;
+    let mut capture = Capture::<1>::new();
+
+    while count.is_none_or(|c| received < c) {
+        let payload = match rx.recv_timeout(Duration::from_secs(1)).await {
+            Ok(payload) => payload,
+            Err(Error::Timeout) if sim.is_some() => break,
+            Err(Error::Timeout) => continue,
+            Err(e) => return Err( format!("{:?}", e) ),
+        };
+
+        received += 1;
+
+        // Time of reception.
+        let time = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+
+        if let Some(file) = pcap.as_mut() {
+            capture.rx(&payload, rx.config(), time.as_micros() as u64);
+            capture.save(file).map_err(|e| e.to_string())?;
+        }
+
+        let time = time.as_secs_f64();
+
+        let pipe = payload.status.pipe().unwrap_or(0);
+
+        if json {
+            println!("{{\"timestamp\":{:.6},\"pipe\":{},\"len\":{},\"data\":\"{}\"}}", time, pipe, payload.view().len(), format(payload.view()));
+        } else {
+            println!("{:.6} pipe {} len {:2} {}", time, pipe, payload.view().len(), format(payload.view()));
+        }
+    }
+
+    rx.stop();
+
+    Ok(())
+}
+
+/// Sweeps the channels with the received power detector.
+async fn scan<SPI: SpiBus + SpiBusWrite, CS: OutputPin, CE: OutputPin, IRQ: Wait>(rx: &mut Receiver<SPI, CS, CE, IRQ>, sweeps: usize) -> Result<(), String> where SPI::Error: Debug {
+    let mut hits = [0usize; Channel::MAX as usize + 1];
+
+    for _ in 0..sweeps {
+        for (channel, hit) in hits.iter_mut().enumerate() {
+            rx.stop();
+            rx.rfchannel(Channel::saturating(channel as u8)).await.map_err(|e| format!("{:?}", e))?;
+            rx.listen().await.map_err(|e| format!("{:?}", e))?;
+
+            // RPD needs 170 us of RX to settle.
+            Timer::after(Duration::from_micros(300)).await;
+
+            let (_, rpd) = rx.base.readreg(Register::Detector).await.map_err(|e| format!("{:?}", e))?;
+
+            *hit += (rpd & 1) as usize;
+        }
+    }
+
+    rx.stop();
+
+    for (channel, hit) in hits.iter().enumerate() {
+        println!("{:3} {} MHz {:4} {}", channel, Channel::saturating(channel as u8).mhz(), hit, "#".repeat((hit * 50).div_ceil(sweeps.max(1))));
+    }
+
+    Ok(())
+}
+
+/// Runs a command against a device.
+async fn run<SPI: SpiBus + SpiBusWrite, CS: OutputPin, CE: OutputPin, IRQ: Wait>(mut base: BaseDriver<SPI, CS, CE, IRQ>, command: Command, sim: Option<&Simulator>) -> Result<(), String> where SPI::Error: Debug {
+    match command {
+        Command::Dump => dump(&mut base).await,
+
+        Command::Set { register, value } => set(&mut base, &register, &value).await,
+
+        Command::Listen { radio, pipes, json, pcap, count, inject } => {
+            if sim.is_none() && !inject.is_empty() {
+                return Err( "--inject requires --sim".into() );
+            }
+
+            let mut rx = Receiver::new(base, radio.config(&pipes)?).await.map_err(|e| format!("{:?}", e))?;
+
+            listen(&mut rx, json, pcap.map(create).transpose()?, count, sim.map(|s| (s, &inject[..]))).await
+        },
+
+        Command::Send { radio, address, data, pcap } => {
+            let pcap = pcap.map(create).transpose()?;
+
+            let mut rx = Receiver::new(base, radio.config(&[])?).await.map_err(|e| format!("{:?}", e))?;
+
+            let address = lsbfirst(&address.0)?;
+
+            let acked = rx.send(address, &data.0, AckPolicy::Required).await.map_err(|e| format!("{:?}", e))?;
+
+            if let Some(mut file) = pcap {
+                let time = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+
+                let mut capture = Capture::<1>::new();
+                capture.tx(address, &data.0, acked, rx.config(), time.as_micros() as u64);
+                capture.save(&mut file).map_err(|e| e.to_string())?;
+            }
+
+            println!("{}", if acked { "acknowledged" } else { "not acknowledged" });
+
+            Ok(())
+        },
+
+        Command::Scan { radio, sweeps, carrier } => {
+            match sim {
+                Some(sim) => carrier.iter().for_each(|c| sim.carrier(*c, true)),
+                _ if !carrier.is_empty() => return Err( "--carrier requires --sim".into() ),
+                _ => (),
+            }
+
+            let mut rx = Receiver::new(base, radio.config(&[])?).await.map_err(|e| format!("{:?}", e))?;
+
+            scan(&mut rx, sweeps).await
+        },
+    }
+}
+
+
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    let result = if cli.sim {
+        let sim = Simulator::new();
+
+        let base = BaseDriver::new(sim.spi(), sim.cs(), sim.ce(), sim.irq());
+
+        run(base, cli.command, Some(&sim)).await
+    } else {
+        // Open the hardware.
+        let device = LinuxSpi::open(&cli.spi, cli.speed).map_err(|e| format!("{}: {}", cli.spi, e))
+            .and_then(|spi| Ok( (spi, linux::output(&cli.gpiochip, cli.ce, false).map_err(|e| format!("CE: {}", e))?) ))
+            .and_then(|(spi, ce)| Ok( (spi, ce, LinuxIrq::open(&cli.gpiochip, cli.irq).map_err(|e| format!("IRQ: {}", e))?) ));
+
+        match device {
+            Ok((spi, ce, irq)) => run(BaseDriver::new(spi, KernelCs, ce, irq), cli.command, None).await,
+            Err(e) => Err(e),
+        }
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        },
+    }
+}
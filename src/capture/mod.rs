@@ -0,0 +1,170 @@
+//! Packet capture in the pcapng format, for analysis in Wireshark.
+//! Frames are stored in a fixed ring buffer, so the capture runs on-device and is drained
+//! over a serial link, or on a Linux gateway straight into a file.
+//!
+//! Every frame carries a link-layer header (LINKTYPE_USER0) of 20 bytes, little endian:
+//!
+//! | Offset | Size | Field                                                         |
+//! |--------|------|---------------------------------------------------------------|
+//! | 0      | 1    | Header version (1)                                            |
+//! | 1      | 1    | Flags: bit 0 set for TX, bit 1 set for ACK                    |
+//! | 2      | 1    | RF channel                                                    |
+//! | 3      | 1    | Pipe of a received frame, `0xFF` if unknown or transmitted    |
+//! | 4      | 1    | Data rate: 0 for 1 Mbps, 1 for 2 Mbps, 2 for 250 kbps         |
+//! | 5      | 1    | Address width in bytes                                        |
+//! | 6      | 5    | Address, LSB first                                            |
+//! | 11     | 1    | Payload length                                                |
+//! | 12     | 8    | Timestamp in microseconds                                     |
+//! | 20     | N    | Payload                                                       |
+//!
+//! The ACK flag is set on transmitted frames that were acknowledged and on received frames
+//! of pipes with auto-acknowledge. A Wireshark dissector is in `tools/wireshark/nrf24.lua`.
+
+
+
+pub mod pcapng;
+mod record;
+
+
+
+pub use record::{ Direction, Record };
+
+
+
+use crate::rx::{
+    Config, Payload,
+};
+
+
+
+pub struct Capture<const N: usize> {
+    /// Ring buffer of frames.
+    records: [Record; N],
+
+    /// Index of the oldest frame.
+    head: usize,
+
+    /// Number of frames stored.
+    len: usize,
+
+    /// Number of frames overwritten before they were drained.
+    dropped: u32,
+
+    /// `true` once the section and interface blocks were written.
+    started: bool,
+}
+
+impl<const N: usize> Capture<N> {
+    /// Creates an empty capture.
+    pub const fn new() -> Self {
+        Self { records: [Record::EMPTY; N], head: 0, len: 0, dropped: 0, started: false, }
+    }
+
+    /// Stores a payload received with the given configuration.
+    pub fn rx(&mut self, payload: &Payload, config: &Config, timestamp: u64) {
+        self.push(Record::rx(payload, config, timestamp))
+    }
+
+    /// Stores a payload transmitted to the given address.
+    pub fn tx(&mut self, address: [u8; 5], data: &[u8], acked: bool, config: &Config, timestamp: u64) {
+        self.push(Record::tx(address, data, acked, config, timestamp))
+    }
+
+    /// Stores a frame, overwriting the oldest one if the buffer is full.
+    pub fn push(&mut self, record: Record) {
+        if N == 0 {
+            self.dropped += 1;
+            return;
+        }
+
+        if self.len == N {
+            self.head = (self.head + 1) % N;
+            self.len -= 1;
+            self.dropped += 1;
+        }
+
+        self.records[(self.head + self.len) % N] = record;
+        self.len += 1;
+    }
+
+    /// Removes the oldest frame.
+    pub fn pop(&mut self) -> Option<Record> {
+        if self.len == 0 {
+            return None;
+        }
+
+        let record = self.records[self.head];
+
+        self.head = (self.head + 1) % N;
+        self.len -= 1;
+
+        Some( record )
+    }
+
+    /// Returns the number of frames stored.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if no frames are stored.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the number of frames overwritten before they were drained.
+    pub fn dropped(&self) -> u32 {
+        self.dropped
+    }
+
+    /// Writes the stored frames as pcapng blocks, oldest first, until the buffer is full.
+    /// The first call also writes the section and interface blocks.
+    /// Returns the number of bytes written.
+    pub fn drain(&mut self, buf: &mut [u8]) -> usize {
+        let mut written = 0;
+
+        if !self.started {
+            if buf.len() < pcapng::SECTION + pcapng::INTERFACE {
+                return 0;
+            }
+
+            written += pcapng::section(buf).unwrap_or(0);
+            written += pcapng::interface(&mut buf[written..]).unwrap_or(0);
+
+            self.started = true;
+        }
+
+        while self.len > 0 {
+            match pcapng::packet(&self.records[self.head], &mut buf[written..]) {
+                Some(n) => written += n,
+                _ => break,
+            }
+
+            self.head = (self.head + 1) % N;
+            self.len -= 1;
+        }
+
+        written
+    }
+
+    /// Writes the stored frames into a pcapng file or stream.
+    #[cfg(feature = "linux")]
+    pub fn save<W: std::io::Write>(&mut self, writer: &mut W) -> std::io::Result<()> {
+        let mut buf = [0; 256];
+
+        loop {
+            let n = self.drain(&mut buf);
+
+            if n == 0 {
+                return writer.flush();
+            }
+
+            writer.write_all(&buf[..n])?;
+        }
+    }
+}
+
+impl<const N: usize> Default for Capture<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,83 @@
+//! Writers of the pcapng blocks of a capture.
+//! Every function returns the number of bytes written, or `None` if the buffer is too small.
+
+
+
+use super::Record;
+
+
+
+/// Link type of the frames (LINKTYPE_USER0).
+pub const LINKTYPE: u16 = 147;
+
+/// Maximum size of a captured frame.
+pub const SNAPLEN: u32 = (Record::HEADER + 32) as u32;
+
+/// Size of the section header block.
+pub const SECTION: usize = 28;
+
+/// Size of the interface description block.
+pub const INTERFACE: usize = 20;
+
+
+
+/// Writes the section header block that starts a capture.
+pub fn section(buf: &mut [u8]) -> Option<usize> {
+    let buf = buf.get_mut(..SECTION)?;
+
+    buf[0..4].copy_from_slice(&0x0A0D0D0Au32.to_le_bytes());
+    buf[4..8].copy_from_slice(&(SECTION as u32).to_le_bytes());
+
+    // Byte order magic and version 1.0.
+    buf[8..12].copy_from_slice(&0x1A2B3C4Du32.to_le_bytes());
+    buf[12..14].copy_from_slice(&1u16.to_le_bytes());
+    buf[14..16].copy_from_slice(&0u16.to_le_bytes());
+
+    // Unknown section length.
+    buf[16..24].copy_from_slice(&(-1i64).to_le_bytes());
+    buf[24..28].copy_from_slice(&(SECTION as u32).to_le_bytes());
+
+    Some( SECTION )
+}
+
+/// Writes the interface description block of the device.
+/// Timestamps use the default resolution of microseconds.
+pub fn interface(buf: &mut [u8]) -> Option<usize> {
+    let buf = buf.get_mut(..INTERFACE)?;
+
+    buf[0..4].copy_from_slice(&1u32.to_le_bytes());
+    buf[4..8].copy_from_slice(&(INTERFACE as u32).to_le_bytes());
+    buf[8..10].copy_from_slice(&LINKTYPE.to_le_bytes());
+    buf[10..12].copy_from_slice(&0u16.to_le_bytes());
+    buf[12..16].copy_from_slice(&SNAPLEN.to_le_bytes());
+    buf[16..20].copy_from_slice(&(INTERFACE as u32).to_le_bytes());
+
+    Some( INTERFACE )
+}
+
+/// Writes the enhanced packet block of a frame.
+pub fn packet(record: &Record, buf: &mut [u8]) -> Option<usize> {
+    // Captured length and its padding to 32 bits.
+    let captured = Record::HEADER + record.view().len();
+    let padded = (captured + 3) & !3;
+
+    let len = 32 + padded;
+    let buf = buf.get_mut(..len)?;
+
+    buf[0..4].copy_from_slice(&6u32.to_le_bytes());
+    buf[4..8].copy_from_slice(&(len as u32).to_le_bytes());
+
+    // Interface 0 and the timestamp split in two words.
+    buf[8..12].copy_from_slice(&0u32.to_le_bytes());
+    buf[12..16].copy_from_slice(&((record.timestamp >> 32) as u32).to_le_bytes());
+    buf[16..20].copy_from_slice(&(record.timestamp as u32).to_le_bytes());
+    buf[20..24].copy_from_slice(&(captured as u32).to_le_bytes());
+    buf[24..28].copy_from_slice(&(captured as u32).to_le_bytes());
+
+    record.encode(&mut buf[28..28 + captured])?;
+
+    buf[28 + captured..28 + padded].fill(0);
+    buf[len - 4..].copy_from_slice(&(len as u32).to_le_bytes());
+
+    Some( len )
+}
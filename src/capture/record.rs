@@ -0,0 +1,140 @@
+//! Captured frames and their link-layer header.
+
+
+
+use crate::{
+    common::{
+        DataRate,
+    },
+    rx::{
+        Config, Payload,
+    },
+};
+
+
+
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub enum Direction {
+    /// Frame received by the device.
+    Rx,
+
+    /// Frame transmitted by the device.
+    Tx,
+}
+
+
+
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub struct Record {
+    /// Time of the frame in microseconds.
+    pub timestamp: u64,
+
+    /// Direction of the frame.
+    pub direction: Direction,
+
+    /// RF channel.
+    pub channel: u8,
+
+    /// Pipe of a received frame.
+    pub pipe: Option<u8>,
+
+    /// Data rate.
+    pub datarate: DataRate,
+
+    /// Address width in bytes.
+    pub width: u8,
+
+    /// Address of the frame, LSB first.
+    pub address: [u8; 5],
+
+    /// `true` if the frame was acknowledged (TX) or the pipe acknowledges (RX).
+    pub ack: bool,
+
+    /// Length of the payload.
+    len: u8,
+
+    /// Raw data of the payload.
+    data: [u8; 32],
+}
+
+impl Record {
+    /// Size of the link-layer header in bytes.
+    pub const HEADER: usize = 20;
+
+    /// Version of the link-layer header.
+    pub const VERSION: u8 = 1;
+
+    /// Empty record, used to initialize buffers.
+    pub const EMPTY: Record = Record { timestamp: 0, direction: Direction::Rx, channel: 0, pipe: None, datarate: DataRate::High, width: 5, address: [0; 5], ack: false, len: 0, data: [0; 32], };
+
+    /// Creates the record of a payload received with the given configuration.
+    pub fn rx(payload: &Payload, config: &Config, timestamp: u64) -> Self {
+        let pipe = payload.status.pipe();
+
+        // Address and auto-acknowledge of the pipe.
+        let (address, ack) = match pipe {
+            Some(n) if n < 6 => (config.rxaddress(n as usize).unwrap_or([0; 5]), config.pipes[n as usize].is_some_and(|p| p.autoack)),
+            _ => ([0; 5], false),
+        };
+
+        let mut record = Self::new(Direction::Rx, config, address, payload.view(), ack, timestamp);
+        record.pipe = pipe;
+
+        record
+    }
+
+    /// Creates the record of a payload transmitted to the given address.
+    pub fn tx(address: [u8; 5], data: &[u8], acked: bool, config: &Config, timestamp: u64) -> Self {
+        Self::new(Direction::Tx, config, address, data, acked, timestamp)
+    }
+
+    /// Returns a view into the payload.
+    pub fn view(&self) -> &[u8] {
+        &self.data[..self.len as usize]
+    }
+
+    /// Writes the link-layer header followed by the payload.
+    /// Returns the number of bytes written, or `None` if the buffer is too small.
+    pub fn encode(&self, buf: &mut [u8]) -> Option<usize> {
+        let len = Self::HEADER + self.len as usize;
+
+        if buf.len() < len {
+            return None;
+        }
+
+        // Flags.
+        let flags = match self.direction {
+            Direction::Rx => 0,
+            Direction::Tx => 1,
+        } | ((self.ack as u8) << 1);
+
+        let datarate = match self.datarate {
+            DataRate::High => 0,
+            DataRate::Max  => 1,
+            DataRate::Low  => 2,
+        };
+
+        buf[0] = Self::VERSION;
+        buf[1] = flags;
+        buf[2] = self.channel;
+        buf[3] = self.pipe.unwrap_or(0xFF);
+        buf[4] = datarate;
+        buf[5] = self.width;
+        buf[6..11].copy_from_slice(&self.address);
+        buf[11] = self.len;
+        buf[12..20].copy_from_slice(&self.timestamp.to_le_bytes());
+        buf[20..len].copy_from_slice(self.view());
+
+        Some( len )
+    }
+
+    /// Creates a record with the metadata of the configuration.
+    fn new(direction: Direction, config: &Config, address: [u8; 5], data: &[u8], ack: bool, timestamp: u64) -> Self {
+        let len = data.len().min(32);
+
+        let mut record = Record { timestamp, direction, channel: config.channel.raw(), pipe: None, datarate: config.datarate, width: config.addrwidth.bytes() as u8, address, ack, len: len as u8, data: [0; 32], };
+        record.data[..len].copy_from_slice(&data[..len]);
+
+        record
+    }
+}
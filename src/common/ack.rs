@@ -0,0 +1,28 @@
+//! Acknowledge policy of transmitted packets.
+
+
+
+use super::Command;
+
+
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum AckPolicy {
+    /// The receiver must acknowledge the packet, which is retransmitted until it does.
+    #[default]
+    Required,
+
+    /// The receiver does not acknowledge the packet (W_TX_PAYLOAD_NOACK).
+    /// Used for broadcast and multicast, where several receivers would answer at once.
+    None,
+}
+
+impl AckPolicy {
+    /// Returns the command that writes a TX payload with this policy.
+    pub(crate) const fn command(&self) -> Command {
+        match self {
+            AckPolicy::Required => Command::TXPayload,
+            AckPolicy::None => Command::TXPayloadNoAck,
+        }
+    }
+}
@@ -0,0 +1,105 @@
+//! RF channels and their regulatory plans.
+
+
+
+use super::{
+    DataRate, Region,
+};
+
+
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Channel(u8);
+
+impl Channel {
+    /// Highest channel of the device (2525 MHz).
+    pub const MAX: u8 = 125;
+
+    /// Frequency of channel 0 in MHz.
+    pub const BASE: u16 = 2400;
+
+    /// Half width of a WiFi channel in MHz, for the 22 MHz mask of 802.11b.
+    pub const WIFIHALFWIDTH: u16 = 11;
+
+    /// Creates a channel.
+    /// Returns `None` if the channel is over `MAX`.
+    pub const fn new(n: u8) -> Option<Self> {
+        match n {
+            0..=Self::MAX => Some( Channel(n) ),
+            _ => None,
+        }
+    }
+
+    /// Creates a channel, limiting it to `MAX`.
+    pub const fn saturating(n: u8) -> Self {
+        match Self::new(n) {
+            Some(channel) => channel,
+            _ => Channel(Self::MAX),
+        }
+    }
+
+    /// Creates the channel of the given frequency in MHz.
+    pub const fn from_mhz(mhz: u16) -> Option<Self> {
+        match mhz.checked_sub(Self::BASE) {
+            Some(n) if n <= Self::MAX as u16 => Some( Channel(n as u8) ),
+            _ => None,
+        }
+    }
+
+    /// Returns the value of the RF_CH register.
+    pub const fn raw(&self) -> u8 {
+        self.0
+    }
+
+    /// Returns the center frequency in MHz.
+    pub const fn mhz(&self) -> u16 {
+        Self::BASE + self.0 as u16
+    }
+
+    /// Returns `true` if the channel and its bandwidth at the data rate fit in the band of the region.
+    pub const fn allowed(&self, region: Region, datarate: DataRate) -> bool {
+        let (low, high) = region.band();
+
+        // Edges of the channel in kHz.
+        let center = self.mhz() as u32 * 1000;
+        let half = datarate.bandwidth() / 2;
+
+        ((center - half) >= low) && ((center + half) <= high)
+    }
+
+    /// Returns `true` if the two channels are far enough apart to not overlap at the data rate.
+    pub const fn spaced(&self, other: Channel, datarate: DataRate) -> bool {
+        self.0.abs_diff(other.0) >= datarate.spacing()
+    }
+
+    /// Returns `true` if the channel does not overlap the given WiFi channel (1 to 14).
+    pub const fn clear_of_wifi(&self, wifi: u8, datarate: DataRate) -> bool {
+        let center = match wifi {
+            14 => 2484,
+            n => 2407 + (5 * n as u16),
+        };
+
+        // Half of the bandwidth in MHz, rounded up.
+        let half = datarate.bandwidth().div_ceil(2000) as u16;
+
+        self.mhz().abs_diff(center) > (Self::WIFIHALFWIDTH + half)
+    }
+
+    /// Writes the channels allowed in the region that are clear of all the given WiFi channels.
+    /// Returns the number of channels written, limited by the length of `out`.
+    pub fn avoid_wifi(wifi: &[u8], region: Region, datarate: DataRate, out: &mut [Channel]) -> usize {
+        let clear = (0..=Self::MAX)
+            .map(Channel)
+            .filter(|c| c.allowed(region, datarate))
+            .filter(|c| wifi.iter().all(|w| c.clear_of_wifi(*w, datarate)));
+
+        let mut n = 0;
+
+        for (slot, channel) in out.iter_mut().zip(clear) {
+            *slot = channel;
+            n += 1;
+        }
+
+        n
+    }
+}
@@ -0,0 +1,71 @@
+//! Variants of the NRF24L01 family and compatible clones.
+
+
+
+use super::Gain;
+
+
+
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub enum ChipVariant {
+    /// Original NRF24L01.
+    /// Lacks the 250 kbps data rate and needs ACTIVATE before FEATURE and DYNPD accept writes.
+    /// Register 0x09 is a carrier detect flag (-85 dBm) instead of the received power detector.
+    Nrf24l01,
+
+    /// NRF24L01+.
+    Nrf24l01Plus,
+
+    /// Si24R1 clone.
+    /// Has a 3 bit PA control with up to +7 dBm and a received power detector at -70 dBm.
+    Si24r1,
+
+    /// Beken BK2423 or BK2425.
+    /// Compatible with the NRF24L01+ in bank 0 once bank 1 is initialized with `Beken::init`.
+    Bk242x,
+}
+
+impl ChipVariant {
+    /// Key of the ACTIVATE command that unlocks FEATURE and DYNPD on the original part.
+    pub const ACTIVATE: u8 = 0x73;
+
+    /// Returns `true` if the variant supports the 250 kbps data rate.
+    pub const fn lowrate(&self) -> bool {
+        !matches!(self, ChipVariant::Nrf24l01)
+    }
+
+    /// Returns `true` if the variant has the CONT_WAVE bit for constant carrier output.
+    pub const fn contwave(&self) -> bool {
+        !matches!(self, ChipVariant::Nrf24l01 | ChipVariant::Bk242x)
+    }
+
+    /// Returns the output power in dBm for the given gain, written in bits 2:1 of RF_SETUP.
+    pub const fn dbm(&self, gain: Gain) -> i8 {
+        match self {
+            ChipVariant::Si24r1 => match gain {
+                Gain::Min  => -12,
+                Gain::Low  =>  -4,
+                Gain::High =>   1,
+                Gain::Max  =>   4,
+            },
+
+            ChipVariant::Bk242x => match gain {
+                Gain::Min  => -10,
+                Gain::Low  =>  -5,
+                Gain::High =>   0,
+                Gain::Max  =>   5,
+            },
+
+            _ => gain.dbm(),
+        }
+    }
+
+    /// Adjusts the RF SETUP register for the variant.
+    /// The original part and the BK242x keep the LNA gain bit set, as after reset.
+    pub(crate) const fn rfsetup(&self, word: u8) -> u8 {
+        match self {
+            ChipVariant::Nrf24l01 | ChipVariant::Bk242x => word | 1,
+            _ => word,
+        }
+    }
+}
@@ -19,6 +19,16 @@ pub enum Command {
     /// Reads the RX payload.
     RXPayload = 0b0110_0001,
 
+    /// Writes the TX payload.
+    TXPayload = 0b1010_0000,
+
+    /// Writes a TX payload that is not acknowledged.
+    /// Needs FEATURE.EN_DYN_ACK.
+    TXPayloadNoAck = 0b1011_0000,
+
+    /// Unlocks the features of the original NRF24L01, followed by a key byte.
+    Activate = 0b0101_0000,
+
     /// No operation.
     Nop = 0b1111_1111,
 }
@@ -2,7 +2,7 @@
 
 
 
-#[derive(Clone, Copy, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum CRCBytes {
     /// One byte used for CRC.
     OneByte,
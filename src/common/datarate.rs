@@ -2,7 +2,7 @@
 
 
 
-#[derive(Clone, Copy, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum DataRate {
     /// 250 kbps data rate.
     Low,
@@ -13,3 +13,21 @@ pub enum DataRate {
     /// 2 Mbps data rate.
     Max,
 }
+
+impl DataRate {
+    /// Returns the occupied bandwidth in kHz.
+    pub const fn bandwidth(&self) -> u32 {
+        match self {
+            DataRate::Max => 2000,
+            _ => 1000,
+        }
+    }
+
+    /// Returns the minimum spacing in channels between two links, so their bandwidths do not overlap.
+    pub const fn spacing(&self) -> u8 {
+        match self {
+            DataRate::Max => 2,
+            _ => 1,
+        }
+    }
+}
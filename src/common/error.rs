@@ -0,0 +1,28 @@
+//! Errors of the NRF24L01(+) driver.
+
+
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Error<E> {
+    /// An error in the SPI bus.
+    Spi(E),
+
+    /// The given address is not valid.
+    Address,
+
+    /// The payload does not fit in a single frame.
+    Length,
+
+    /// The configuration is not supported by the device.
+    Unsupported,
+
+    /// No payload arrived in time.
+    Timeout,
+
+    /// No pipe is free for the address.
+    NoPipe,
+
+    /// The frame counter of a secure link ran out.
+    /// The link must be paired again for a new key before sending more.
+    Exhausted,
+}
@@ -2,17 +2,87 @@
 
 
 
-#[derive(Clone, Copy, Eq, PartialEq)]
+use super::ChipVariant;
+
+
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum Gain {
-    /// Minimum gain (0 dB).
+    /// Minimum gain (-18 dBm).
     Min,
 
-    /// Low gain (0 dB).
+    /// Low gain (-12 dBm).
     Low,
 
-    /// High gain (0 dB).
+    /// High gain (-6 dBm).
     High,
 
-    /// Maximum gain (0 dB).
+    /// Maximum gain (0 dBm).
     Max,
 }
+
+impl Gain {
+    /// All the levels, from the lowest to the highest.
+    pub const ALL: [Gain; 4] = [Gain::Min, Gain::Low, Gain::High, Gain::Max];
+
+    /// Returns the output power of the NRF24L01(+) at this level in dBm.
+    pub const fn dbm(&self) -> i8 {
+        match self {
+            Gain::Min  => -18,
+            Gain::Low  => -12,
+            Gain::High =>  -6,
+            Gain::Max  =>   0,
+        }
+    }
+}
+
+
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct TxPower {
+    /// Variant of the device, which sets the output power of each level.
+    pub chip: ChipVariant,
+
+    /// Level of the device.
+    pub gain: Gain,
+
+    /// Gain of the external PA of the board in dB.
+    pub offset: i8,
+}
+
+impl TxPower {
+    /// Creates the output power of a level of the device with the given external PA gain.
+    pub const fn new(chip: ChipVariant, gain: Gain, offset: i8) -> Self {
+        TxPower { chip, gain, offset }
+    }
+
+    /// Returns the level of the device whose output power is closest to the target, with the
+    /// given external PA gain.
+    /// Ties pick the lower level.
+    pub const fn closest(chip: ChipVariant, target: i8, offset: i8) -> Self {
+        let mut best = Gain::Min;
+        let mut i = 1;
+
+        while i < Gain::ALL.len() {
+            let gain = Gain::ALL[i];
+
+            if (chip.dbm(gain) as i16 + offset as i16 - target as i16).abs() < (chip.dbm(best) as i16 + offset as i16 - target as i16).abs() {
+                best = gain;
+            }
+
+            i += 1;
+        }
+
+        TxPower { chip, gain: best, offset }
+    }
+
+    /// Returns the conducted output power of the board in dBm.
+    pub const fn dbm(&self) -> i8 {
+        self.chip.dbm(self.gain).saturating_add(self.offset)
+    }
+
+    /// Returns the radiated power in dBm with the given antenna gain in dBi.
+    pub const fn eirp(&self, antenna: i8) -> i8 {
+        self.dbm().saturating_add(antenna)
+    }
+}
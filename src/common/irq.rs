@@ -0,0 +1,97 @@
+//! Interrupt sources of NRF24L01(+) devices.
+//! The same bits flag the events in STATUS and mask them from the IRQ pin in CONFIG.
+
+
+
+use core::ops::{
+    BitAnd, BitOr, Not,
+};
+
+use super::Status;
+
+
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[repr(transparent)]
+pub struct IrqEvents(u8);
+
+impl IrqEvents {
+    /// No events.
+    pub const NONE: Self = IrqEvents(0);
+
+    /// A new payload arrived in the RX FIFO (RX_DR).
+    pub const RX_READY: Self = IrqEvents(1 << 6);
+
+    /// A payload was transmitted, and acknowledged if auto-acknowledge is on (TX_DS).
+    pub const TX_SENT: Self = IrqEvents(1 << 5);
+
+    /// The maximum number of retransmits was reached (MAX_RT).
+    pub const MAX_RETRIES: Self = IrqEvents(1 << 4);
+
+    /// All the events.
+    pub const ALL: Self = IrqEvents(0b111 << 4);
+
+    /// Returns the events flagged in the status.
+    pub const fn from_status(status: Status) -> Self {
+        IrqEvents(status.0 & Self::ALL.0)
+    }
+
+    /// Returns the raw bits of the events.
+    pub const fn bits(&self) -> u8 {
+        self.0
+    }
+
+    /// Returns `true` if there are no events.
+    pub const fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// Returns `true` if all the given events are in the set.
+    pub const fn contains(&self, other: Self) -> bool {
+        (self.0 & other.0) == other.0
+    }
+
+    /// Returns the events of both sets.
+    pub const fn union(self, other: Self) -> Self {
+        IrqEvents(self.0 | other.0)
+    }
+
+    /// Returns `true` if a new payload arrived in the RX FIFO.
+    pub const fn rxready(&self) -> bool {
+        self.contains(Self::RX_READY)
+    }
+
+    /// Returns `true` if a payload was transmitted.
+    pub const fn txsent(&self) -> bool {
+        self.contains(Self::TX_SENT)
+    }
+
+    /// Returns `true` if the maximum number of retransmits was reached.
+    pub const fn maxretries(&self) -> bool {
+        self.contains(Self::MAX_RETRIES)
+    }
+}
+
+impl BitOr for IrqEvents {
+    type Output = Self;
+
+    fn bitor(self, other: Self) -> Self {
+        self.union(other)
+    }
+}
+
+impl BitAnd for IrqEvents {
+    type Output = Self;
+
+    fn bitand(self, other: Self) -> Self {
+        IrqEvents(self.0 & other.0)
+    }
+}
+
+impl Not for IrqEvents {
+    type Output = Self;
+
+    fn not(self) -> Self {
+        IrqEvents(!self.0 & Self::ALL.0)
+    }
+}
@@ -2,11 +2,16 @@
 
 
 
+mod ack;
+mod channel;
+mod chip;
 mod command;
 mod crc;
 mod datarate;
+mod error;
 mod gain;
-mod pipe;
+mod irq;
+mod region;
 mod register;
 mod state;
 mod status;
@@ -14,11 +19,16 @@ mod width;
 
 
 
+pub use ack::*;
+pub use channel::*;
+pub use chip::*;
 pub use command::*;
 pub use crc::*;
 pub use datarate::*;
+pub use error::*;
 pub use gain::*;
-pub use pipe::*;
+pub use irq::*;
+pub use region::*;
 pub use register::*;
 pub use state::*;
 pub use status::*;
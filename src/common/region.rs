@@ -0,0 +1,48 @@
+//! Regulatory regions of the 2.4 GHz ISM band.
+
+
+
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub enum Region {
+    /// United States, FCC part 15.247.
+    Fcc,
+
+    /// Europe, ETSI EN 300 328.
+    Etsi,
+
+    /// Japan, ARIB STD-T66.
+    Japan,
+}
+
+impl Region {
+    /// Returns the edges in kHz of the part of the band a channel may occupy.
+    pub const fn band(&self) -> (u32, u32) {
+        match self {
+            // 2400 to 2483.5 MHz, but the restricted band of part 15.205 starts at the upper
+            // edge, so the top 2 MHz are kept free for the GFSK sidelobes.
+            Region::Fcc => (2_400_000, 2_481_500),
+
+            // 2400 to 2483.5 MHz.
+            Region::Etsi => (2_400_000, 2_483_500),
+
+            // 2400 to 2483.5 MHz. The 2471 to 2497 MHz band of RCR STD-33 needs spread spectrum
+            // modulation, which the device lacks.
+            Region::Japan => (2_400_000, 2_483_500),
+        }
+    }
+
+    /// Returns the maximum radiated power (EIRP) in dBm.
+    /// Power density limits are taken over the 1 MHz bandwidth of a 1 Mbps channel.
+    pub const fn max_eirp(&self) -> i8 {
+        match self {
+            // 1 W conducted with a 6 dBi antenna.
+            Region::Fcc => 36,
+
+            // 100 mW for wideband modulation.
+            Region::Etsi => 20,
+
+            // 10 mW/MHz.
+            Region::Japan => 10,
+        }
+    }
+}
@@ -8,5 +8,38 @@
 pub struct Status(pub(crate) u8);
 
 impl Status {
+    /// Returns `true` if a new payload arrived in the RX FIFO.
+    pub const fn rxready(&self) -> bool {
+        (self.0 & (1 << 6)) != 0
+    }
 
+    /// Returns `true` if a payload was transmitted (and acknowledged, if auto-acknowledge is on).
+    pub const fn txsent(&self) -> bool {
+        (self.0 & (1 << 5)) != 0
+    }
+
+    /// Returns `true` if the maximum number of retransmits was reached.
+    pub const fn maxretries(&self) -> bool {
+        (self.0 & (1 << 4)) != 0
+    }
+
+    /// Returns the pipe of the payload at the head of the RX FIFO.
+    /// Returns `None` if the RX FIFO is empty.
+    pub const fn pipe(&self) -> Option<u8> {
+        match (self.0 >> 1) & 0b111 {
+            pipe @ 0..=5 => Some(pipe),
+            _ => None,
+        }
+    }
+
+    /// Returns the register bank selected in BK242x devices.
+    /// Always 0 in other devices.
+    pub const fn bank(&self) -> u8 {
+        self.0 >> 7
+    }
+
+    /// Returns `true` if the TX FIFO is full.
+    pub const fn txfull(&self) -> bool {
+        (self.0 & 1) != 0
+    }
 }
@@ -2,8 +2,12 @@
 
 
 
-#[derive(Clone, Copy, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum AddressWidth {
+    /// Address width of 2 bytes.
+    /// Not documented by the datasheet (SETUP_AW of `0b00`), only used by the sniffer.
+    TwoBytes,
+
     /// Address width of 3 bytes.
     ThreeBytes,
 
@@ -13,3 +17,15 @@ pub enum AddressWidth {
     /// Address width of 5 bytes.
     FiveBytes,
 }
+
+impl AddressWidth {
+    /// Returns the width in bytes.
+    pub const fn bytes(&self) -> usize {
+        match self {
+            AddressWidth::TwoBytes   => 2,
+            AddressWidth::ThreeBytes => 3,
+            AddressWidth::FourBytes  => 4,
+            AddressWidth::FiveBytes  => 5,
+        }
+    }
+}
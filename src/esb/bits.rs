@@ -0,0 +1,47 @@
+//! Bit level access to frames sent over the air, MSB first.
+
+
+
+/// Returns the bit at the given position.
+pub(crate) const fn bit(data: &[u8], i: usize) -> bool {
+    ((data[i / 8] >> (7 - (i % 8))) & 1) != 0
+}
+
+/// Returns the byte starting at the given bit position.
+pub(crate) const fn byte(data: &[u8], i: usize) -> u8 {
+    let shift = i % 8;
+
+    if shift == 0 {
+        return data[i / 8];
+    }
+
+    (data[i / 8] << shift) | (data[i / 8 + 1] >> (8 - shift))
+}
+
+/// Returns the bits starting at the given position as the low bits of a word.
+pub(crate) const fn word(data: &[u8], i: usize, bits: usize) -> u32 {
+    let mut word = 0;
+    let mut n = 0;
+
+    while n < bits {
+        word = (word << 1) | (bit(data, i + n) as u32);
+        n += 1;
+    }
+
+    word
+}
+
+/// Writes the low bits of a word starting at the given position.
+pub(crate) fn put(data: &mut [u8], i: usize, word: u32, bits: usize) {
+    for n in 0..bits {
+        // Position of the bit.
+        let p = i + n;
+        let mask = 1 << (7 - (p % 8));
+
+        if ((word >> (bits - 1 - n)) & 1) != 0 {
+            data[p / 8] |= mask;
+        } else {
+            data[p / 8] &= !mask;
+        }
+    }
+}
@@ -0,0 +1,55 @@
+//! Software CRC of Enhanced ShockBurst frames.
+//! The CRC covers the address, the packet control field and the payload bit by bit, so it
+//! works on frames that are not aligned to bytes.
+
+
+
+use super::bits;
+
+
+
+/// Polynomial of the 1 byte CRC (x^8 + x^2 + x + 1).
+pub const CRC8POLY: u8 = 0x07;
+
+/// Polynomial of the 2 byte CRC-16-CCITT (x^16 + x^12 + x^5 + 1).
+pub const CRC16POLY: u16 = 0x1021;
+
+/// Computes the 1 byte CRC of `len` bits of the data, starting at bit `start`.
+pub const fn crc8(data: &[u8], start: usize, len: usize) -> u8 {
+    let mut crc = 0xFF;
+    let mut i = 0;
+
+    while i < len {
+        let feedback = ((crc >> 7) != 0) ^ bits::bit(data, start + i);
+
+        crc <<= 1;
+
+        if feedback {
+            crc ^= CRC8POLY;
+        }
+
+        i += 1;
+    }
+
+    crc
+}
+
+/// Computes the 2 byte CRC of `len` bits of the data, starting at bit `start`.
+pub const fn crc16(data: &[u8], start: usize, len: usize) -> u16 {
+    let mut crc = 0xFFFF;
+    let mut i = 0;
+
+    while i < len {
+        let feedback = ((crc >> 15) != 0) ^ bits::bit(data, start + i);
+
+        crc <<= 1;
+
+        if feedback {
+            crc ^= CRC16POLY;
+        }
+
+        i += 1;
+    }
+
+    crc
+}
@@ -0,0 +1,222 @@
+//! Bit level codec of complete frames, as sent over the air.
+//!
+//! An Enhanced ShockBurst frame is made of a preamble byte, the address (3 to 5 bytes, MSB
+//! first), the 9 bit packet control field, the payload and the CRC (1 or 2 bytes). The CRC
+//! covers the address, the packet control field and the payload. Legacy ShockBurst frames
+//! have no packet control field and a static payload length.
+
+
+
+use crate::common::{
+    AddressWidth, CRCBytes,
+};
+
+use super::{
+    bits, crc, Pcf, Preamble,
+};
+
+
+
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub enum Format {
+    /// Enhanced ShockBurst, with a packet control field.
+    Enhanced,
+
+    /// Legacy ShockBurst, with the given static payload length.
+    Legacy(u8),
+}
+
+
+
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub struct Frame {
+    /// Address width.
+    pub width: AddressWidth,
+
+    /// Address, LSB first.
+    pub address: [u8; 5],
+
+    /// Packet control field, `None` for legacy ShockBurst.
+    pub pcf: Option<Pcf>,
+
+    /// CRC of the frame, `None` if disabled.
+    pub crc: Option<CRCBytes>,
+
+    /// Length of the payload.
+    len: u8,
+
+    /// Raw payload.
+    data: [u8; 32],
+}
+
+impl Frame {
+    /// Maximum size of an encoded frame in bytes.
+    pub const MAXSIZE: usize = (8 + 40 + Pcf::BITS + 256 + 16).div_ceil(8);
+
+    /// Creates an Enhanced ShockBurst frame.
+    /// Returns `None` if the payload is longer than 32 bytes.
+    pub fn enhanced(width: AddressWidth, address: [u8; 5], pid: u8, noack: bool, crc: Option<CRCBytes>, payload: &[u8]) -> Option<Self> {
+        let mut frame = Self::legacy(width, address, crc, payload)?;
+        frame.pcf = Some( Pcf::new(payload.len() as u8, pid, noack) );
+
+        Some( frame )
+    }
+
+    /// Creates a legacy ShockBurst frame.
+    /// Returns `None` if the payload is longer than 32 bytes.
+    pub fn legacy(width: AddressWidth, address: [u8; 5], crc: Option<CRCBytes>, payload: &[u8]) -> Option<Self> {
+        if payload.len() > 32 {
+            return None;
+        }
+
+        let mut data = [0; 32];
+        data[..payload.len()].copy_from_slice(payload);
+
+        Some( Frame { width, address, pcf: None, crc, len: payload.len() as u8, data } )
+    }
+
+    /// Returns a view into the payload.
+    pub fn view(&self) -> &[u8] {
+        &self.data[..self.len as usize]
+    }
+
+    /// Returns the preamble sent before the frame.
+    pub fn preamble(&self) -> Preamble {
+        Preamble::of(&self.address[..self.width.bytes()])
+    }
+
+    /// Returns the size of the encoded frame in bits, preamble included.
+    pub fn bits(&self) -> usize {
+        8 + self.covered() + Self::crcbits(self.crc)
+    }
+
+    /// Encodes the frame, preamble included, padding the last byte with zeros.
+    /// Returns the size in bits, or `None` if the buffer is too small.
+    pub fn encode(&self, buf: &mut [u8]) -> Option<usize> {
+        let len = self.bits();
+
+        let buf = buf.get_mut(..len.div_ceil(8))?;
+        buf.fill(0);
+
+        buf[0] = self.preamble().address()[0];
+
+        let covered = self.covered();
+
+        // Address, MSB first.
+        let w = self.width.bytes();
+
+        for i in 0..w {
+            buf[1 + i] = self.address[w - 1 - i];
+        }
+
+        let mut p = 8 + (w * 8);
+
+        if let Some(pcf) = self.pcf {
+            bits::put(buf, p, pcf.encode() as u32, Pcf::BITS);
+            p += Pcf::BITS;
+        }
+
+        for byte in self.view() {
+            bits::put(buf, p, *byte as u32, 8);
+            p += 8;
+        }
+
+        match self.crc {
+            Some(CRCBytes::OneByte) => bits::put(buf, p, crc::crc8(buf, 8, covered) as u32, 8),
+            Some(CRCBytes::TwoBytes) => bits::put(buf, p, crc::crc16(buf, 8, covered) as u32, 16),
+            _ => (),
+        }
+
+        Some( len )
+    }
+
+    /// Decodes a frame that starts with its preamble.
+    /// Returns `None` if the preamble does not match the address, the frame is truncated or the CRC fails.
+    pub fn decode(raw: &[u8], width: AddressWidth, crc: Option<CRCBytes>, format: Format) -> Option<Self> {
+        let frame = Self::decode_at(raw, 8, width, crc, format)?;
+
+        if raw[0] != frame.preamble().address()[0] {
+            return None;
+        }
+
+        Some( frame )
+    }
+
+    /// Decodes a frame whose address starts at the given bit offset, without preamble.
+    /// Returns `None` if the frame is truncated or the CRC fails.
+    pub fn decode_at(raw: &[u8], offset: usize, width: AddressWidth, crc: Option<CRCBytes>, format: Format) -> Option<Self> {
+        let available = (raw.len() * 8).checked_sub(offset)?;
+
+        let w = width.bytes();
+        let crcbits = Self::crcbits(crc);
+
+        // Packet control field and payload length.
+        let (pcf, len) = match format {
+            Format::Enhanced => {
+                if available < (w * 8) + Pcf::BITS {
+                    return None;
+                }
+
+                let pcf = Pcf::decode( bits::word(raw, offset + (w * 8), Pcf::BITS) as u16 );
+
+                (Some( pcf ), pcf.len as usize)
+            },
+
+            Format::Legacy(len) => (None, len as usize),
+        };
+
+        if len > 32 {
+            return None;
+        }
+
+        // Bits covered by the CRC.
+        let header = (w * 8) + pcf.map_or(0, |_| Pcf::BITS);
+        let covered = header + (len * 8);
+
+        if available < covered + crcbits {
+            return None;
+        }
+
+        // Check the CRC.
+        let received = bits::word(raw, offset + covered, crcbits);
+
+        let valid = match crc {
+            Some(CRCBytes::OneByte) => crc::crc8(raw, offset, covered) as u32 == received,
+            Some(CRCBytes::TwoBytes) => crc::crc16(raw, offset, covered) as u32 == received,
+            _ => true,
+        };
+
+        if !valid {
+            return None;
+        }
+
+        // The address is sent MSB first.
+        let mut address = [0; 5];
+
+        for (i, byte) in address[..w].iter_mut().enumerate() {
+            *byte = bits::byte(raw, offset + ((w - 1 - i) * 8));
+        }
+
+        let mut data = [0; 32];
+
+        for (i, byte) in data[..len].iter_mut().enumerate() {
+            *byte = bits::byte(raw, offset + header + (i * 8));
+        }
+
+        Some( Frame { width, address, pcf, crc, len: len as u8, data } )
+    }
+
+    /// Returns the bits covered by the CRC.
+    fn covered(&self) -> usize {
+        (self.width.bytes() * 8) + self.pcf.map_or(0, |_| Pcf::BITS) + (self.len as usize * 8)
+    }
+
+    /// Returns the size of the CRC in bits.
+    const fn crcbits(crc: Option<CRCBytes>) -> usize {
+        match crc {
+            Some(CRCBytes::OneByte) => 8,
+            Some(CRCBytes::TwoBytes) => 16,
+            _ => 0,
+        }
+    }
+}
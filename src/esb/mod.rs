@@ -0,0 +1,17 @@
+//! Enhanced ShockBurst protocol, as sent over the air.
+//! Implements in software the parts the device handles internally, so frames can be
+//! encoded for the simulator and decoded by the sniffer and offline analysis.
+
+
+
+mod bits;
+pub mod crc;
+pub mod frame;
+mod pcf;
+mod sniff;
+
+
+
+pub use frame::{ Format, Frame };
+pub use pcf::Pcf;
+pub use sniff::{ Preamble, Sniffed };
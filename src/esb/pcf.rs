@@ -0,0 +1,35 @@
+//! Packet control field of Enhanced ShockBurst frames.
+
+
+
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub struct Pcf {
+    /// Length of the payload (6 bits).
+    pub len: u8,
+
+    /// Packet identifier, used to detect retransmissions (2 bits).
+    pub pid: u8,
+
+    /// `true` if the receiver must not acknowledge the frame.
+    pub noack: bool,
+}
+
+impl Pcf {
+    /// Size of the field in bits.
+    pub const BITS: usize = 9;
+
+    /// Creates a packet control field.
+    pub const fn new(len: u8, pid: u8, noack: bool) -> Self {
+        Pcf { len: len & 0x3F, pid: pid & 0b11, noack }
+    }
+
+    /// Decodes the field from the low 9 bits of a word.
+    pub const fn decode(word: u16) -> Self {
+        Pcf { len: ((word >> 3) & 0x3F) as u8, pid: ((word >> 1) & 0b11) as u8, noack: (word & 1) != 0 }
+    }
+
+    /// Encodes the field into the low 9 bits of a word.
+    pub const fn encode(&self) -> u16 {
+        (((self.len & 0x3F) as u16) << 3) | (((self.pid & 0b11) as u16) << 1) | (self.noack as u16)
+    }
+}
@@ -0,0 +1,70 @@
+//! Decoding of the raw frames captured in promiscuous mode.
+//! The device listens with a 2 byte address made of a noise byte and a preamble, without CRC,
+//! so any frame on the channel is captured with its address, packet control field and CRC.
+
+
+
+use crate::common::{
+    AddressWidth, CRCBytes,
+};
+
+use super::{
+    Format, Frame,
+};
+
+
+
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub enum Preamble {
+    /// Preamble `0xAA`, sent before addresses starting with a 1 bit.
+    High,
+
+    /// Preamble `0x55`, sent before addresses starting with a 0 bit.
+    Low,
+}
+
+impl Preamble {
+    /// Returns the preamble sent before the given address (LSB first).
+    pub const fn of(address: &[u8]) -> Self {
+        match address.last() {
+            Some(msb) if (*msb & 0x80) == 0 => Preamble::Low,
+            _ => Preamble::High,
+        }
+    }
+
+    /// Returns the sniffer address that matches the preamble, LSB first.
+    pub const fn address(&self) -> [u8; 5] {
+        match self {
+            Preamble::High => [0xAA, 0x00, 0x00, 0x00, 0x00],
+            Preamble::Low  => [0x55, 0x00, 0x00, 0x00, 0x00],
+        }
+    }
+}
+
+
+
+#[derive(Clone)]
+pub struct Sniffed {
+    /// Decoded frame.
+    pub frame: Frame,
+
+    /// Bit offset of the frame in the captured data.
+    pub offset: usize,
+}
+
+impl Sniffed {
+    /// Bit offsets tried when decoding.
+    pub const OFFSETS: usize = 8;
+
+    /// Decodes a raw capture, trying every bit offset until the CRC matches.
+    pub fn decode(raw: &[u8], width: AddressWidth, crc: CRCBytes) -> Option<Self> {
+        (0..Self::OFFSETS).find_map(|offset| {
+            Frame::decode_at(raw, offset, width, Some( crc ), Format::Enhanced).map(|frame| Sniffed { frame, offset })
+        })
+    }
+
+    /// Returns a view into the payload.
+    pub fn view(&self) -> &[u8] {
+        self.frame.view()
+    }
+}
@@ -0,0 +1,81 @@
+//! Control of external PA/LNA front ends, like the RFX2401C in nRF24L01+PA+LNA boards.
+//! The base driver switches the front end with the role of the device: to TX or RX right
+//! before CE rises, and off right after CE falls or the device powers down. The front end
+//! settles in under 1 us, well inside the 130 us the device takes to settle its PLL after CE.
+//!
+//! While waiting for an acknowledge the device turns around to RX internally, which the pins
+//! cannot follow. The front end stays in TX, so boards that route the LNA only through RXEN
+//! receive acknowledges with the reduced sensitivity of the bypass path.
+
+
+
+use embedded_hal::{
+    digital::v2::{
+        OutputPin,
+    },
+};
+
+
+
+pub trait FrontEnd {
+    /// Switches the front end to transmit.
+    fn tx(&mut self);
+
+    /// Switches the front end to receive.
+    fn rx(&mut self);
+
+    /// Switches the front end off.
+    fn off(&mut self);
+}
+
+
+
+/// No front end, or a front end driven by the device itself.
+pub struct NoFrontEnd;
+
+impl FrontEnd for NoFrontEnd {
+    fn tx(&mut self) {}
+
+    fn rx(&mut self) {}
+
+    fn off(&mut self) {}
+}
+
+
+
+/// Front end with TXEN and RXEN control pins.
+pub struct PaLna<TXEN: OutputPin, RXEN: OutputPin> {
+    /// Enables the PA.
+    txen: TXEN,
+
+    /// Enables the LNA.
+    rxen: RXEN,
+}
+
+impl<TXEN: OutputPin, RXEN: OutputPin> PaLna<TXEN, RXEN> {
+    /// Takes ownership of the control pins and switches the front end off.
+    pub fn new(txen: TXEN, rxen: RXEN) -> Self {
+        let mut frontend = Self { txen, rxen };
+        frontend.off();
+
+        frontend
+    }
+}
+
+impl<TXEN: OutputPin, RXEN: OutputPin> FrontEnd for PaLna<TXEN, RXEN> {
+    fn tx(&mut self) {
+        // Disable before enable, so both paths are never on together.
+        let _ = self.rxen.set_low();
+        let _ = self.txen.set_high();
+    }
+
+    fn rx(&mut self) {
+        let _ = self.txen.set_low();
+        let _ = self.rxen.set_high();
+    }
+
+    fn off(&mut self) {
+        let _ = self.txen.set_low();
+        let _ = self.rxen.set_low();
+    }
+}
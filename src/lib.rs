@@ -6,6 +6,26 @@
 
 
 
+#[cfg(feature = "linux")]
+extern crate std;
+
+
+
 pub mod base;
+pub mod beken;
+pub mod capture;
 pub mod common;
+pub mod esb;
+pub mod frontend;
+#[cfg(feature = "linux")]
+pub mod linux;
+pub mod mesh;
+pub mod multicast;
+pub mod network;
+pub mod radio;
 pub mod rx;
+#[cfg(feature = "secure")]
+pub mod secure;
+#[cfg(feature = "sim")]
+pub mod sim;
+pub mod test_mode;
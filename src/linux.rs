@@ -0,0 +1,243 @@
+//! Linux backend of the driver, for gateways like the Raspberry Pi.
+//! Provides the SPI bus over spidev, the output pins over the GPIO character device and
+//! the IRQ pin over asynchronous GPIO edge events, which require a tokio runtime.
+
+
+
+use std::{
+    io, vec,
+};
+
+use embedded_hal::{
+    digital::v2::{
+        OutputPin,
+    },
+};
+
+use embedded_hal_1::{
+    digital,
+};
+
+use embedded_hal_async::{
+    digital::{
+        Wait,
+    },
+    spi::{
+        self, ErrorType, SpiBus, SpiBusFlush, SpiBusRead, SpiBusWrite,
+    },
+};
+
+use futures::StreamExt;
+
+use gpio_cdev::{
+    AsyncLineEventHandle, Chip, EventRequestFlags, LineRequestFlags,
+};
+
+use linux_embedded_hal::{
+    spidev::{
+        SpiModeFlags, Spidev, SpidevOptions, SpidevTransfer,
+    },
+};
+
+pub use linux_embedded_hal::CdevPin;
+
+
+
+/// Opens a GPIO line as an output pin.
+/// Use `/dev/gpiochip0` and the BCM pin number on a Raspberry Pi.
+pub fn output(chip: &str, line: u32, high: bool) -> Result<CdevPin, gpio_cdev::Error> {
+    let handle = Chip::new(chip)?
+        .get_line(line)?
+        .request(LineRequestFlags::OUTPUT, high as u8, "nrf24")?;
+
+    CdevPin::new(handle)
+}
+
+
+
+pub struct LinuxSpi(Spidev);
+
+impl LinuxSpi {
+    /// Maximum SPI clock of the device.
+    pub const MAXSPEED: u32 = 10_000_000;
+
+    /// Opens the SPI device (for example `/dev/spidev0.0`) in mode 0 at the given clock.
+    pub fn open(path: &str, speed: u32) -> io::Result<Self> {
+        let mut spi = Spidev::open(path)?;
+
+        let options = SpidevOptions::new()
+            .bits_per_word(8)
+            .max_speed_hz(speed.min(Self::MAXSPEED))
+            .mode(SpiModeFlags::SPI_MODE_0)
+            .build();
+
+        spi.configure(&options)?;
+
+        Ok( Self(spi) )
+    }
+}
+
+impl ErrorType for LinuxSpi {
+    type Error = LinuxError;
+}
+
+impl SpiBusFlush for LinuxSpi {
+    async fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+impl SpiBusRead for LinuxSpi {
+    async fn read(&mut self, words: &mut [u8]) -> Result<(), Self::Error> {
+        self.0.transfer(&mut SpidevTransfer::read(words)).map_err(LinuxError::Io)
+    }
+}
+
+impl SpiBusWrite for LinuxSpi {
+    async fn write(&mut self, words: &[u8]) -> Result<(), Self::Error> {
+        self.0.transfer(&mut SpidevTransfer::write(words)).map_err(LinuxError::Io)
+    }
+}
+
+impl SpiBus for LinuxSpi {
+    async fn transfer<'a>(&'a mut self, read: &'a mut [u8], write: &'a [u8]) -> Result<(), Self::Error> {
+        if read.len() == write.len() {
+            return self.0.transfer(&mut SpidevTransfer::read_write(write, read)).map_err(LinuxError::Io);
+        }
+
+        // Pad both sides to the longer one, so the transfer is a single ioctl and CS stays low.
+        let len = read.len().max(write.len());
+
+        let mut output = vec![0; len];
+        output[..write.len()].copy_from_slice(write);
+
+        let mut input = vec![0; len];
+
+        self.0.transfer(&mut SpidevTransfer::read_write(&output, &mut input)).map_err(LinuxError::Io)?;
+
+        read.copy_from_slice(&input[..read.len()]);
+
+        Ok(())
+    }
+
+    async fn transfer_in_place<'a>(&'a mut self, words: &'a mut [u8]) -> Result<(), Self::Error> {
+        // Spidev needs separate buffers, and the whole transfer is a single ioctl.
+        let output = words.to_vec();
+
+        self.0.transfer(&mut SpidevTransfer::read_write(&output, words)).map_err(LinuxError::Io)
+    }
+}
+
+
+/// Chip select pin for devices where the kernel drives the chip select line of the SPI bus.
+pub struct KernelCs;
+
+impl OutputPin for KernelCs {
+    type Error = core::convert::Infallible;
+
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+
+
+pub struct LinuxIrq(AsyncLineEventHandle);
+
+impl LinuxIrq {
+    /// Opens a GPIO line as the IRQ input.
+    /// Must be called inside a tokio runtime.
+    pub fn open(chip: &str, line: u32) -> Result<Self, gpio_cdev::Error> {
+        let events = Chip::new(chip)?
+            .get_line(line)?
+            .events(LineRequestFlags::INPUT, EventRequestFlags::BOTH_EDGES, "nrf24-irq")?;
+
+        Ok( Self( AsyncLineEventHandle::new(events)? ) )
+    }
+
+    /// Returns `true` if the line is high.
+    fn high(&self) -> Result<bool, LinuxError> {
+        self.0.as_ref().get_value().map(|v| v != 0).map_err(LinuxError::Gpio)
+    }
+
+    /// Waits for the next edge of the given kind, or any edge if `None`.
+    async fn edge(&mut self, rising: Option<bool>) -> Result<(), LinuxError> {
+        while let Some(event) = self.0.next().await {
+            let event = event.map_err(LinuxError::Gpio)?;
+
+            let kind = match event.event_type() {
+                gpio_cdev::EventType::RisingEdge => true,
+                gpio_cdev::EventType::FallingEdge => false,
+            };
+
+            if rising.unwrap_or(kind) == kind {
+                return Ok(());
+            }
+        }
+
+        Err( LinuxError::Io( io::ErrorKind::UnexpectedEof.into() ) )
+    }
+}
+
+impl digital::ErrorType for LinuxIrq {
+    type Error = LinuxError;
+}
+
+impl Wait for LinuxIrq {
+    async fn wait_for_high(&mut self) -> Result<(), Self::Error> {
+        // Events already queued only trigger a new check of the line.
+        while !self.high()? {
+            self.edge(None).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn wait_for_low(&mut self) -> Result<(), Self::Error> {
+        // Events already queued only trigger a new check of the line.
+        while self.high()? {
+            self.edge(None).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn wait_for_rising_edge(&mut self) -> Result<(), Self::Error> {
+        self.edge(Some(true)).await
+    }
+
+    async fn wait_for_falling_edge(&mut self) -> Result<(), Self::Error> {
+        self.edge(Some(false)).await
+    }
+
+    async fn wait_for_any_edge(&mut self) -> Result<(), Self::Error> {
+        self.edge(None).await
+    }
+}
+
+
+
+#[derive(Debug)]
+pub enum LinuxError {
+    /// An error in the SPI device.
+    Io(io::Error),
+
+    /// An error in the GPIO character device.
+    Gpio(gpio_cdev::Error),
+}
+
+impl spi::Error for LinuxError {
+    fn kind(&self) -> spi::ErrorKind {
+        spi::ErrorKind::Other
+    }
+}
+
+impl digital::Error for LinuxError {
+    fn kind(&self) -> digital::ErrorKind {
+        digital::ErrorKind::Other
+    }
+}
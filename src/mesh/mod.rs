@@ -0,0 +1,452 @@
+//! RF24Mesh compatible self-organizing mesh layer.
+//! Nodes are identified by a unique node ID and request their logical address from the master,
+//! which keeps the table of node IDs and addresses.
+
+
+
+use embassy_time::{
+    Duration, Instant, Timer,
+};
+
+use embedded_hal::{
+    digital::v2::{
+        OutputPin,
+    },
+};
+
+use embedded_hal_async::{
+    digital::{
+        Wait,
+    },
+    spi::{
+        SpiBus, SpiBusWrite,
+    },
+};
+
+use crate::{
+    common::{
+        AckPolicy, Error,
+    },
+    frontend::{
+        FrontEnd, NoFrontEnd,
+    },
+    network::{
+        Address, Frame, Header, Network,
+    },
+};
+
+
+
+/// Maximum number of nodes that answer a poll.
+const MAXPOLLS: usize = 4;
+
+
+
+pub struct Mesh<SPI: SpiBus + SpiBusWrite, CS: OutputPin, CE: OutputPin, IRQ: Wait, const N: usize, FE: FrontEnd = NoFrontEnd> {
+    /// The underlying network.
+    pub network: Network<SPI, CS, CE, IRQ, FE>,
+
+    /// Node ID of this node. The master always has node ID 0.
+    id: u8,
+
+    /// Table of node IDs and addresses kept by the master.
+    table: [(u8, u16); N],
+
+    /// Number of entries in the table.
+    len: usize,
+}
+
+impl<SPI: SpiBus + SpiBusWrite, CS: OutputPin, CE: OutputPin, IRQ: Wait, const N: usize, FE: FrontEnd> Mesh<SPI, CS, CE, IRQ, N, FE> {
+    /// Confirmation of a newly assigned address.
+    pub const MESH_ADDR_CONFIRM: u8 = 129;
+
+    /// Lookup of the address of a node ID.
+    pub const MESH_ADDR_LOOKUP: u8 = 196;
+
+    /// Release of the address of a node.
+    pub const MESH_ADDR_RELEASE: u8 = 197;
+
+    /// Lookup of the node ID of an address.
+    pub const MESH_ID_LOOKUP: u8 = 198;
+
+    /// Time to wait for the answers to a poll.
+    const POLLTIME: Duration = Duration::from_millis(55);
+
+    /// Time to wait for the response to an address request.
+    const RESPONSETIME: Duration = Duration::from_millis(225);
+
+    /// Time to wait for the response to a lookup.
+    const LOOKUPTIME: Duration = Duration::from_millis(135);
+
+    /// Creates the mesh layer over a network.
+    /// A node ID of 0 makes this node the master.
+    pub fn new(network: Network<SPI, CS, CE, IRQ, FE>, id: u8) -> Self {
+        Self { network, id, table: [(0, 0); N], len: 0, }
+    }
+
+    /// Returns the node ID of this node.
+    pub fn id(&self) -> u8 {
+        self.id
+    }
+
+    /// Returns `true` if this node is the master.
+    pub fn master(&self) -> bool {
+        self.id == 0
+    }
+
+    /// Joins the mesh, polling every level of the tree until an address is assigned or the timeout expires.
+    pub async fn begin(&mut self, timeout: Duration) -> Result<Option<Address>, Error<SPI::Error>> {
+        // The master always has the master address.
+        if self.master() {
+            self.network.readdress(Address::MASTER).await?;
+
+            return Ok( Some( Address::MASTER ) );
+        }
+
+        self.network.readdress(Address::DEFAULT).await?;
+
+        let deadline = Instant::now() + timeout;
+
+        // Level to poll.
+        let mut level = 0;
+
+        while Instant::now() < deadline {
+            if let Some(address) = self.request(level).await? {
+                return Ok( Some( address ) );
+            }
+
+            level = (level + 1) % 4;
+        }
+
+        Ok( None )
+    }
+
+    /// Releases the current address and requests a new one.
+    /// Nodes must renew their address when their parent disappears.
+    pub async fn renew(&mut self, timeout: Duration) -> Result<Option<Address>, Error<SPI::Error>> {
+        self.release().await?;
+
+        self.begin(timeout).await
+    }
+
+    /// Releases the address of this node in the master.
+    pub async fn release(&mut self) -> Result<bool, Error<SPI::Error>> {
+        if self.master() || (self.network.address() == Address::DEFAULT) {
+            return Ok( false );
+        }
+
+        let header = Header::new(self.network.address().raw(), Address::MASTER.raw(), self.network.id(), Self::MESH_ADDR_RELEASE);
+
+        let released = self.network.route(header, &[]).await?;
+
+        self.network.readdress(Address::DEFAULT).await?;
+
+        Ok( released )
+    }
+
+    /// Checks the connection with the master by looking up the address of this node.
+    pub async fn check(&mut self) -> Result<bool, Error<SPI::Error>> {
+        if self.master() {
+            return Ok( true );
+        }
+
+        let address = self.lookup(self.id).await?;
+
+        Ok( address == Some( self.network.address() ) )
+    }
+
+    /// Looks up the address of the given node ID.
+    pub async fn lookup(&mut self, id: u8) -> Result<Option<Address>, Error<SPI::Error>> {
+        // The master knows the table.
+        if self.master() {
+            return Ok( self.address(id).and_then(Address::new) );
+        }
+
+        let header = Header::new(self.network.address().raw(), Address::MASTER.raw(), self.network.id(), Self::MESH_ADDR_LOOKUP);
+
+        if !self.network.route(header, &[id]).await? {
+            return Ok( None );
+        }
+
+        match self.wait(Self::MESH_ADDR_LOOKUP, Self::LOOKUPTIME).await? {
+            Some(frame) if frame.view().len() >= 2 => {
+                let raw = u16::from_le_bytes([frame.view()[0], frame.view()[1]]);
+
+                Ok( Address::new(raw) )
+            },
+
+            _ => Ok( None ),
+        }
+    }
+
+    /// Looks up the node ID of the given address.
+    pub async fn lookup_id(&mut self, address: Address) -> Result<Option<u8>, Error<SPI::Error>> {
+        // The master knows the table.
+        if self.master() {
+            return Ok( self.nodeid(address.raw()) );
+        }
+
+        let header = Header::new(self.network.address().raw(), Address::MASTER.raw(), self.network.id(), Self::MESH_ID_LOOKUP);
+
+        if !self.network.route(header, &address.raw().to_le_bytes()).await? {
+            return Ok( None );
+        }
+
+        match self.wait(Self::MESH_ID_LOOKUP, Self::LOOKUPTIME).await? {
+            Some(frame) if frame.view().len() >= 2 => match i16::from_le_bytes([frame.view()[0], frame.view()[1]]) {
+                id @ 0..=255 => Ok( Some( id as u8 ) ),
+                _ => Ok( None ),
+            },
+
+            _ => Ok( None ),
+        }
+    }
+
+    /// Sends a message to the node with the given node ID.
+    /// If the message is not delivered and the master cannot be reached, the address of this node is renewed.
+    pub async fn send(&mut self, id: u8, kind: u8, message: &[u8]) -> Result<bool, Error<SPI::Error>> {
+        // Resolve the address of the node.
+        let address = match id {
+            0 => Address::MASTER,
+
+            _ => match self.lookup(id).await? {
+                Some(address) => address,
+                _ => return Ok( false ),
+            },
+        };
+
+        if self.network.send(address.raw(), kind, message).await? {
+            return Ok( true );
+        }
+
+        // Re-attach to the mesh if the parent disappeared.
+        if !self.check().await? {
+            self.renew(Self::RESPONSETIME * 4).await?;
+        }
+
+        Ok( false )
+    }
+
+    /// Receives the next frame addressed to this node.
+    /// In the master, this also serves the address requests and lookups of the other nodes.
+    pub async fn recv(&mut self, timeout: Duration) -> Result<Option<Frame>, Error<SPI::Error>> {
+        let frame = match self.network.recv(timeout).await? {
+            Some(frame) => frame,
+            _ => return Ok( None ),
+        };
+
+        if !self.master() {
+            return Ok( Some( frame ) );
+        }
+
+        let header = frame.header;
+        let message = frame.view();
+
+        match header.kind {
+            Header::NETWORK_REQ_ADDRESS => self.dhcp(header).await?,
+
+            Self::MESH_ADDR_LOOKUP if !message.is_empty() => {
+                let address = match self.address(message[0]) {
+                    Some(address) => address as i16,
+                    _ => -1,
+                };
+
+                let reply = Header { from: Address::MASTER.raw(), to: header.from, ..header };
+
+                self.network.route(reply, &address.to_le_bytes()).await?;
+            },
+
+            Self::MESH_ID_LOOKUP if message.len() >= 2 => {
+                let id = match self.nodeid(u16::from_le_bytes([message[0], message[1]])) {
+                    Some(id) => id as i16,
+                    _ => -1,
+                };
+
+                let reply = Header { from: Address::MASTER.raw(), to: header.from, ..header };
+
+                self.network.route(reply, &id.to_le_bytes()).await?;
+            },
+
+            Self::MESH_ADDR_RELEASE => {
+                for entry in self.table[..self.len].iter_mut() {
+                    if entry.1 == header.from {
+                        entry.1 = Address::DEFAULT.raw();
+                    }
+                }
+            },
+
+            Self::MESH_ADDR_CONFIRM => (),
+
+            _ => return Ok( Some( frame ) ),
+        }
+
+        Ok( None )
+    }
+
+    /// Returns the table of node IDs and addresses kept by the master.
+    pub fn table(&self) -> &[(u8, u16)] {
+        &self.table[..self.len]
+    }
+
+    /// Requests an address from the nodes in the given level.
+    async fn request(&mut self, level: u8) -> Result<Option<Address>, Error<SPI::Error>> {
+        // Poll the nodes in the level.
+        let poll = Header::new(Address::DEFAULT.raw(), Address::MULTICAST, self.network.id(), Header::NETWORK_POLL);
+
+        self.network.write(Address::multicast_address(level), poll, &[], AckPolicy::None).await?;
+
+        // Collect the nodes that answer.
+        let mut contacts = [0u16; MAXPOLLS];
+        let mut count = 0;
+
+        let deadline = Instant::now() + Self::POLLTIME;
+
+        while (count < MAXPOLLS) && (Instant::now() < deadline) {
+            if let Some(frame) = self.network.recv_deadline(deadline).await? {
+                if (frame.header.kind == Header::NETWORK_POLL) && !contacts[..count].contains(&frame.header.from) {
+                    contacts[count] = frame.header.from;
+                    count += 1;
+                }
+            }
+        }
+
+        // Request an address through each contact.
+        for contact in contacts[..count].iter() {
+            let mut header = Header::new(Address::DEFAULT.raw(), *contact, self.network.id(), Header::NETWORK_REQ_ADDRESS);
+            header.reserved = self.id;
+
+            self.network.direct(*contact, header, &[]).await?;
+
+            // Wait for the response.
+            let response = loop {
+                match self.wait(Header::NETWORK_ADDR_RESPONSE, Self::RESPONSETIME).await? {
+                    Some(frame) if frame.header.reserved != self.id => continue,
+                    response => break response,
+                }
+            };
+
+            let frame = match response {
+                Some(frame) if frame.view().len() >= 2 => frame,
+                _ => continue,
+            };
+
+            // Check that the address is a child of the contact.
+            let address = match Address::new(u16::from_le_bytes([frame.view()[0], frame.view()[1]])) {
+                Some(address) if address.parent().raw() == *contact => address,
+                _ => continue,
+            };
+
+            self.network.readdress(address).await?;
+
+            // Confirm the address to the master.
+            let confirm = Header::new(address.raw(), Address::MASTER.raw(), self.network.id(), Self::MESH_ADDR_CONFIRM);
+
+            self.network.route(confirm, &[]).await?;
+
+            return Ok( Some( address ) );
+        }
+
+        Ok( None )
+    }
+
+    /// Assigns an address to the node that sent the request.
+    async fn dhcp(&mut self, header: Header) -> Result<(), Error<SPI::Error>> {
+        // Node ID of the requester.
+        let id = header.reserved;
+
+        if id == 0 {
+            return Ok(());
+        }
+
+        // Parent of the new node, and its number of children.
+        let (parent, children) = if header.from == Address::DEFAULT.raw() {
+            (Address::MASTER, 5)
+        } else {
+            match Address::new(header.from) {
+                Some(parent) if parent.level() < 4 => (parent, 4),
+                _ => return Ok(()),
+            }
+        };
+
+        // Find a free child address.
+        for child in (1..=children).rev() {
+            let address = parent.raw() | (child << (3 * parent.level() as u16));
+
+            if address == Address::DEFAULT.raw() {
+                continue;
+            }
+
+            if self.table[..self.len].iter().any(|entry| (entry.1 == address) && (entry.0 != id)) {
+                continue;
+            }
+
+            // Store the address of the node.
+            if !self.assign(id, address) {
+                return Ok(());
+            }
+
+            // Wait for the requester to listen for the response.
+            Timer::after(Duration::from_millis(2)).await;
+
+            let response = Header { from: Address::MASTER.raw(), to: header.from, kind: Header::NETWORK_ADDR_RESPONSE, ..header };
+
+            if header.from == Address::DEFAULT.raw() {
+                self.network.direct(header.from, response, &address.to_le_bytes()).await?;
+            } else {
+                self.network.route(response, &address.to_le_bytes()).await?;
+            }
+
+            break;
+        }
+
+        Ok(())
+    }
+
+    /// Stores the address of a node ID in the table.
+    /// Returns `false` if the table is full.
+    fn assign(&mut self, id: u8, address: u16) -> bool {
+        // Update an existing entry.
+        if let Some(entry) = self.table[..self.len].iter_mut().find(|entry| entry.0 == id) {
+            entry.1 = address;
+
+            return true;
+        }
+
+        if self.len == N {
+            return false;
+        }
+
+        self.table[self.len] = (id, address);
+        self.len += 1;
+
+        true
+    }
+
+    /// Returns the address of the given node ID from the table.
+    fn address(&self, id: u8) -> Option<u16> {
+        self.table[..self.len].iter()
+            .find(|entry| (entry.0 == id) && (entry.1 != Address::DEFAULT.raw()))
+            .map(|entry| entry.1)
+    }
+
+    /// Returns the node ID of the given address from the table.
+    fn nodeid(&self, address: u16) -> Option<u8> {
+        self.table[..self.len].iter()
+            .find(|entry| entry.1 == address)
+            .map(|entry| entry.0)
+    }
+
+    /// Waits for a frame of the given type.
+    async fn wait(&mut self, kind: u8, timeout: Duration) -> Result<Option<Frame>, Error<SPI::Error>> {
+        let deadline = Instant::now() + timeout;
+
+        while Instant::now() < deadline {
+            match self.network.recv_deadline(deadline).await? {
+                Some(frame) if frame.header.kind == kind => return Ok( Some( frame ) ),
+                _ => (),
+            }
+        }
+
+        Ok( None )
+    }
+}
@@ -0,0 +1,330 @@
+//! Multicast groups over the shared prefix of pipes 1 to 5.
+//! Nodes subscribe to group addresses mapped onto the spare pipes 2 to 5, and senders address a
+//! group with unacknowledged frames repeated a few times. Receivers drop the repeated copies and,
+//! if the sender asks for it, answer with an application level acknowledge that the sender collects.
+
+
+
+use embassy_time::{
+    Duration, Instant, Timer,
+};
+
+use embedded_hal::{
+    digital::v2::{
+        OutputPin,
+    },
+};
+
+use embedded_hal_async::{
+    digital::{
+        Wait,
+    },
+    spi::{
+        SpiBus, SpiBusWrite,
+    },
+};
+
+use crate::{
+    common::{
+        AckPolicy, Error,
+    },
+    frontend::{
+        FrontEnd, NoFrontEnd,
+    },
+    rx::{
+        Pipe, PipeAddress, Receiver,
+    },
+};
+
+
+
+/// Number of senders remembered to drop repeated frames.
+const MAXSEEN: usize = 8;
+
+/// Size of the header: kind, sequence number, sender and reply address.
+pub const HEADER: usize = 8;
+
+/// Maximum size of a message.
+pub const MAXMESSAGE: usize = 32 - HEADER;
+
+
+
+#[derive(Clone, Copy)]
+pub struct Message {
+    /// Pipe of the group the message was sent to.
+    /// `None` if it was sent to this node alone.
+    pub group: Option<u8>,
+
+    /// Node ID of the sender.
+    pub from: u8,
+
+    /// Sequence number of the message.
+    pub seq: u8,
+
+    /// Length of the message.
+    len: usize,
+
+    /// Raw data of the message.
+    data: [u8; MAXMESSAGE],
+}
+
+impl Message {
+    /// Returns a view into the message.
+    pub fn view(&self) -> &[u8] {
+        &self.data[..self.len]
+    }
+}
+
+
+
+pub struct Multicast<SPI: SpiBus + SpiBusWrite, CS: OutputPin, CE: OutputPin, IRQ: Wait, FE: FrontEnd = NoFrontEnd> {
+    /// The underlying radio.
+    pub radio: Receiver<SPI, CS, CE, IRQ, FE>,
+
+    /// Node ID of this node.
+    id: u8,
+
+    /// Sequence number of the next message.
+    next: u8,
+
+    /// Pipes subscribed to a group, one bit per pipe.
+    groups: u8,
+
+    /// Last sender and sequence numbers received.
+    seen: [Option<(u8, u8)>; MAXSEEN],
+
+    /// Index of the next entry of `seen` to replace.
+    oldest: usize,
+
+    /// Delay per node ID before acknowledging a message.
+    stagger: Duration,
+}
+
+impl<SPI: SpiBus + SpiBusWrite, CS: OutputPin, CE: OutputPin, IRQ: Wait, FE: FrontEnd> Multicast<SPI, CS, CE, IRQ, FE> {
+    /// Message that does not need an acknowledge.
+    pub const DATA: u8 = 0xD0;
+
+    /// Message whose receivers acknowledge it to the reply address.
+    pub const DATA_ACK: u8 = 0xD1;
+
+    /// Acknowledge of a message.
+    pub const ACK: u8 = 0xDA;
+
+    /// Time between the repeated copies of a message.
+    pub const SPACING: Duration = Duration::from_micros(500);
+
+    /// Creates the multicast layer over a receiver.
+    /// Acknowledges are received in pipe 1, which must have auto-acknowledge enabled.
+    pub fn new(radio: Receiver<SPI, CS, CE, IRQ, FE>, id: u8) -> Self {
+        Self { radio, id, next: 0, groups: 0, seen: [None; MAXSEEN], oldest: 0, stagger: Self::SPACING, }
+    }
+
+    /// Sets the delay per node ID before acknowledging a message, `SPACING` by default.
+    /// Node `id` waits `(id % 16)` times the delay, so the acknowledges of the receivers of a
+    /// group do not collide. A zero delay answers at once, for groups with a single receiver.
+    pub fn stagger(&mut self, delay: Duration) {
+        self.stagger = delay;
+    }
+
+    /// Returns the node ID of this node.
+    pub fn id(&self) -> u8 {
+        self.id
+    }
+
+    /// Subscribes to the group and returns the pipe it is mapped onto.
+    /// The group must share the address MSBs of pipes 1 to 5.
+    /// Fails with `Address` if it does not or its LSB is used by another pipe, and with `NoPipe`
+    /// if pipes 2 to 5 are all in use.
+    pub async fn subscribe(&mut self, group: [u8; 5]) -> Result<u8, Error<SPI::Error>> {
+        let mut config = *self.radio.config();
+
+        // The group must share the MSBs within the address width.
+        let len = config.addrwidth.bytes() - 1;
+
+        if group[1..=len] != config.prefix()[..len] {
+            return Err( Error::Address );
+        }
+
+        // Check if the LSB is in use.
+        for n in 1..6 {
+            match config.pipes[n] {
+                Some(pipe) if pipe.address.lsb() == group[0] => match (self.groups & (1 << n)) != 0 {
+                    true => return Ok( n as u8 ),
+                    _ => return Err( Error::Address ),
+                },
+
+                _ => (),
+            }
+        }
+
+        // Map the group onto a spare pipe.
+        let n = match (2..6).find(|n| config.pipes[*n].is_none()) {
+            Some(n) => n,
+            _ => return Err( Error::NoPipe ),
+        };
+
+        config.pipes[n] = Some( Pipe::dynamic(PipeAddress::Lsb(group[0]), false) );
+
+        self.radio.configure(config).await?;
+
+        self.groups |= 1 << n;
+
+        Ok( n as u8 )
+    }
+
+    /// Unsubscribes from the group and frees its pipe.
+    /// Returns `false` if this node was not subscribed to it.
+    pub async fn unsubscribe(&mut self, group: [u8; 5]) -> Result<bool, Error<SPI::Error>> {
+        let mut config = *self.radio.config();
+
+        let n = match (2..6).find(|n| ((self.groups & (1 << n)) != 0) && config.pipes[*n].is_some_and(|p| p.address.lsb() == group[0])) {
+            Some(n) => n,
+            _ => return Ok( false ),
+        };
+
+        config.pipes[n] = None;
+
+        self.radio.configure(config).await?;
+
+        self.groups &= !(1 << n);
+
+        Ok( true )
+    }
+
+    /// Sends a message to the group, repeated the given number of extra times.
+    /// Delivery is best effort, as group frames are not acknowledged.
+    pub async fn publish(&mut self, group: [u8; 5], message: &[u8], repeats: u8) -> Result<(), Error<SPI::Error>> {
+        let seq = self.seq();
+
+        self.repeat(group, Self::DATA, seq, [0; 5], message, repeats).await
+    }
+
+    /// Sends a message to the group, repeated the given number of extra times, and collects the
+    /// acknowledges of its receivers during the window.
+    /// Stores the node IDs of the receivers in `acks` and returns their number.
+    pub async fn publish_acked(&mut self, group: [u8; 5], message: &[u8], repeats: u8, window: Duration, acks: &mut [u8]) -> Result<usize, Error<SPI::Error>> {
+        // Acknowledges come back to pipe 1.
+        let reply = match self.radio.config().rxaddress(1) {
+            Some(address) => address,
+            _ => return Err( Error::Address ),
+        };
+
+        let seq = self.seq();
+
+        self.repeat(group, Self::DATA_ACK, seq, reply, message, repeats).await?;
+
+        // Collect the acknowledges.
+        let mut count = 0;
+
+        let deadline = Instant::now() + window;
+
+        while count < acks.len() {
+            let payload = match self.radio.recv_deadline(deadline).await {
+                Ok(payload) => payload,
+                Err(Error::Timeout) => break,
+                Err(e) => return Err( e ),
+            };
+
+            let data = payload.view();
+
+            if (data.len() < HEADER) || (data[0] != Self::ACK) || (data[1] != seq) {
+                continue;
+            }
+
+            if !acks[..count].contains(&data[2]) {
+                acks[count] = data[2];
+                count += 1;
+            }
+        }
+
+        Ok( count )
+    }
+
+    /// Receives the next message sent to a subscribed group or to this node.
+    /// Foreign frames and repeated copies are skipped until the timeout, after acknowledging the
+    /// copies if asked. Acknowledging waits for the stagger of this node first, up to 15 times
+    /// the delay set with `stagger`.
+    /// Fails with `Timeout` if no new message arrived in time.
+    pub async fn recv(&mut self, timeout: Duration) -> Result<Message, Error<SPI::Error>> {
+        let deadline = Instant::now() + timeout;
+
+        loop {
+            let payload = self.radio.recv_deadline(deadline).await?;
+
+            let data = payload.view();
+
+            if (data.len() < HEADER) || ((data[0] != Self::DATA) && (data[0] != Self::DATA_ACK)) {
+                continue;
+            }
+
+            let (kind, seq, from) = (data[0], data[1], data[2]);
+
+            // Acknowledge every copy, in case an earlier acknowledge was lost.
+            if kind == Self::DATA_ACK {
+                let reply = <[u8; 5]>::try_from(&data[3..HEADER]).unwrap();
+
+                let mut frame = [0; HEADER];
+                frame[..3].copy_from_slice(&[Self::ACK, seq, self.id]);
+
+                // Stagger the acknowledges of the receivers of the group.
+                Timer::after(self.stagger * (self.id % 16) as u32).await;
+
+                self.radio.send(reply, &frame, AckPolicy::Required).await.map_err(Error::Spi)?;
+            }
+
+            // Drop repeated copies.
+            if self.seen.contains(&Some( (from, seq) )) {
+                continue;
+            }
+
+            self.seen[self.oldest] = Some( (from, seq) );
+            self.oldest = (self.oldest + 1) % MAXSEEN;
+
+            let group = match payload.status.pipe() {
+                Some(n) if (self.groups & (1 << n)) != 0 => Some( n ),
+                _ => None,
+            };
+
+            let message = &data[HEADER..];
+
+            let mut buffer = [0; MAXMESSAGE];
+            buffer[..message.len()].copy_from_slice(message);
+
+            return Ok( Message { group, from, seq, len: message.len(), data: buffer } );
+        }
+    }
+
+    /// Sends the copies of a frame to the group.
+    async fn repeat(&mut self, group: [u8; 5], kind: u8, seq: u8, reply: [u8; 5], message: &[u8], repeats: u8) -> Result<(), Error<SPI::Error>> {
+        // Check the length.
+        if message.len() > MAXMESSAGE {
+            return Err( Error::Length );
+        }
+
+        // Build the frame.
+        let mut frame = [0; 32];
+        frame[..3].copy_from_slice(&[kind, seq, self.id]);
+        frame[3..HEADER].copy_from_slice(&reply);
+        frame[HEADER..HEADER + message.len()].copy_from_slice(message);
+
+        let frame = &frame[..HEADER + message.len()];
+
+        for i in 0..=repeats {
+            if i > 0 {
+                Timer::after(Self::SPACING).await;
+            }
+
+            self.radio.send(group, frame, AckPolicy::None).await.map_err(Error::Spi)?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the sequence number of the next message.
+    fn seq(&mut self) -> u8 {
+        let seq = self.next;
+        self.next = self.next.wrapping_add(1);
+
+        seq
+    }
+}
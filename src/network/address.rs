@@ -0,0 +1,146 @@
+//! Logical addresses of the RF24Network tree.
+//! Each octal digit of an address is the child index (1 to 5) at one level of the tree.
+//! The least significant digit is the level closest to the master node (`0o0`).
+
+
+
+#[derive(Clone, Copy, Eq, PartialEq)]
+#[repr(transparent)]
+pub struct Address(pub(crate) u16);
+
+impl Address {
+    /// Address of the master node.
+    pub const MASTER: Address = Address(0);
+
+    /// Address of nodes that have not joined the network yet.
+    pub const DEFAULT: Address = Address(0o4444);
+
+    /// Logical address used as destination of multicast frames.
+    pub const MULTICAST: u16 = 0o100;
+
+    /// Translation of octal digits and pipes to radio address bytes.
+    const TRANSLATION: [u8; 7] = [0xC3, 0x3C, 0x33, 0xCE, 0x3E, 0xE3, 0xEC];
+
+    /// Creates a new address.
+    /// Returns `None` if any digit is out of the 1 to 5 range or the tree is deeper than 4 levels.
+    pub const fn new(node: u16) -> Option<Self> {
+        // Remaining digits.
+        let mut digits = node;
+
+        // Number of levels.
+        let mut count = 0;
+
+        while digits != 0 {
+            match digits & 0b111 {
+                1..=5 => (),
+                _ => return None,
+            }
+
+            digits >>= 3;
+            count += 1;
+        }
+
+        if count > 4 {
+            return None;
+        }
+
+        Some( Address(node) )
+    }
+
+    /// Returns the raw logical address.
+    pub const fn raw(&self) -> u16 {
+        self.0
+    }
+
+    /// Returns the level of the node in the tree (0 for the master).
+    pub const fn level(&self) -> u8 {
+        // Remaining digits.
+        let mut digits = self.0;
+
+        // Number of levels.
+        let mut count = 0;
+
+        while digits != 0 {
+            digits >>= 3;
+            count += 1;
+        }
+
+        count
+    }
+
+    /// Returns the mask covering the digits of this address.
+    const fn mask(&self) -> u16 {
+        (1 << (3 * self.level() as u16)) - 1
+    }
+
+    /// Returns the parent of this node.
+    /// The master is its own parent.
+    pub const fn parent(&self) -> Address {
+        Address( self.0 & (self.mask() >> 3) )
+    }
+
+    /// Returns the pipe this node uses to talk to its parent.
+    /// This is the last digit of the address.
+    pub const fn parent_pipe(&self) -> u8 {
+        match self.level() {
+            0 => 0,
+            l => (self.0 >> (3 * (l as u16 - 1))) as u8,
+        }
+    }
+
+    /// Returns `true` if the given node is below this node in the tree.
+    pub const fn is_descendant(&self, node: u16) -> bool {
+        (node != self.0) && ((node & self.mask()) == self.0)
+    }
+
+    /// Returns `true` if the given node is a child of this node.
+    pub const fn is_direct_child(&self, node: u16) -> bool {
+        self.is_descendant(node) && ((node & !((self.mask() << 3) | 0b111)) == 0)
+    }
+
+    /// Returns the child of this node through which the given descendant is reached.
+    pub const fn route_to(&self, node: u16) -> u16 {
+        node & ((self.mask() << 3) | 0b111)
+    }
+
+    /// Returns the radio address of the given pipe of this node.
+    /// Pipe 0 is the multicast pipe of the node's level, except for the master.
+    pub const fn pipe_address(&self, pipe: u8) -> [u8; 5] {
+        // Radio address, LSB first.
+        let mut out = [0xCC; 5];
+
+        // Remaining digits.
+        let mut digits = self.0;
+
+        // Current byte.
+        let mut count = 1;
+
+        // Multicast pipes do not encode the node.
+        let multicast = (pipe == 0) && (self.0 != 0);
+
+        while digits != 0 {
+            if !multicast {
+                out[count] = Self::TRANSLATION[(digits & 0b111) as usize];
+            }
+
+            digits >>= 3;
+            count += 1;
+        }
+
+        if multicast {
+            out[1] = Self::TRANSLATION[count - 1];
+        } else {
+            out[0] = Self::TRANSLATION[pipe as usize];
+        }
+
+        out
+    }
+
+    /// Returns the radio address of the multicast pipe of the given level.
+    pub const fn multicast_address(level: u8) -> [u8; 5] {
+        match level {
+            0 => Self::MASTER.pipe_address(0),
+            l => Address( 1 << (3 * (l as u16 - 1)) ).pipe_address(0),
+        }
+    }
+}
@@ -0,0 +1,78 @@
+//! RF24Network frame header.
+//! The header is 8 bytes long and is sent little endian at the start of every frame.
+
+
+
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub struct Header {
+    /// Logical address of the sender.
+    pub from: u16,
+
+    /// Logical address of the destination.
+    pub to: u16,
+
+    /// Sequence number of the frame.
+    pub id: u16,
+
+    /// Message type.
+    /// Types 0 to 127 are user types, types 65 to 127 are acknowledged by the network.
+    pub kind: u8,
+
+    /// Reserved byte, used by the upper layers.
+    pub reserved: u8,
+}
+
+impl Header {
+    /// Size of the header in bytes.
+    pub const SIZE: usize = 8;
+
+    /// Response to an address request.
+    pub const NETWORK_ADDR_RESPONSE: u8 = 128;
+
+    /// Ping of a node.
+    pub const NETWORK_PING: u8 = 130;
+
+    /// Acknowledge of a routed frame.
+    pub const NETWORK_ACK: u8 = 193;
+
+    /// Poll for nodes that can accept children.
+    pub const NETWORK_POLL: u8 = 194;
+
+    /// Request of a new address to the master.
+    pub const NETWORK_REQ_ADDRESS: u8 = 195;
+
+    /// Creates a new header.
+    pub const fn new(from: u16, to: u16, id: u16, kind: u8) -> Self {
+        Header { from, to, id, kind, reserved: 0 }
+    }
+
+    /// Returns `true` if the final hop must acknowledge this frame to the sender.
+    pub const fn acknowledged(&self) -> bool {
+        (self.kind > 64) && (self.kind < 128)
+    }
+
+    /// Serializes the header.
+    pub const fn bytes(&self) -> [u8; 8] {
+        let from = self.from.to_le_bytes();
+        let to = self.to.to_le_bytes();
+        let id = self.id.to_le_bytes();
+
+        [from[0], from[1], to[0], to[1], id[0], id[1], self.kind, self.reserved]
+    }
+
+    /// Deserializes a header.
+    /// Returns `None` if the data is shorter than a header.
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        if data.len() < Self::SIZE {
+            return None;
+        }
+
+        Some( Header {
+            from: u16::from_le_bytes([data[0], data[1]]),
+            to: u16::from_le_bytes([data[2], data[3]]),
+            id: u16::from_le_bytes([data[4], data[5]]),
+            kind: data[6],
+            reserved: data[7],
+        } )
+    }
+}
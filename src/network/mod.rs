@@ -0,0 +1,310 @@
+//! RF24Network compatible tree network layer.
+//! Nodes are arranged in a tree rooted at the master node and frames are relayed
+//! through parents and children until they reach their destination.
+
+
+
+mod address;
+mod header;
+
+
+
+pub use address::Address;
+pub use header::Header;
+
+
+
+use embassy_time::{
+    Duration, Instant, Timer,
+};
+
+use embedded_hal::{
+    digital::v2::{
+        OutputPin,
+    },
+};
+
+use embedded_hal_async::{
+    digital::{
+        Wait,
+    },
+    spi::{
+        SpiBus, SpiBusWrite,
+    },
+};
+
+use crate::{
+    common::{
+        AckPolicy, AddressWidth, Channel, CRCBytes, DataRate, Error, Gain,
+    },
+    frontend::{
+        FrontEnd, NoFrontEnd,
+    },
+    rx::{
+        Config, Pipe, PipeAddress, Receiver,
+    },
+};
+
+
+
+pub struct Network<SPI: SpiBus + SpiBusWrite, CS: OutputPin, CE: OutputPin, IRQ: Wait, FE: FrontEnd = NoFrontEnd> {
+    /// The underlying radio.
+    pub radio: Receiver<SPI, CS, CE, IRQ, FE>,
+
+    /// Logical address of this node.
+    node: Address,
+
+    /// Sequence number of the next frame.
+    next: u16,
+
+    /// Relay multicast frames to the next level.
+    relay: bool,
+}
+
+impl<SPI: SpiBus + SpiBusWrite, CS: OutputPin, CE: OutputPin, IRQ: Wait, FE: FrontEnd> Network<SPI, CS, CE, IRQ, FE> {
+    /// Maximum size of the message carried by a frame.
+    pub const MAXMESSAGE: usize = 32 - Header::SIZE;
+
+    /// Builds the receiver configuration of the given node.
+    /// Pipe 0 listens to the multicast address of the node's level and pipes 1 to 5 to its children and parent.
+    pub const fn config(node: Address, channel: Channel, datarate: DataRate, gain: Gain) -> Config {
+        // Multicast address.
+        let multicast = node.pipe_address(0);
+
+        // Node address.
+        let main = node.pipe_address(1);
+
+        Config::configured(
+            channel,
+            datarate,
+            gain,
+            AddressWidth::FiveBytes,
+            Some( CRCBytes::TwoBytes ),
+            [
+                Some( Pipe::dynamic(PipeAddress::Full(multicast), false) ),
+                Some( Pipe::dynamic(PipeAddress::Full(main), true) ),
+                Some( Pipe::dynamic(PipeAddress::Lsb(node.pipe_address(2)[0]), true) ),
+                Some( Pipe::dynamic(PipeAddress::Lsb(node.pipe_address(3)[0]), true) ),
+                Some( Pipe::dynamic(PipeAddress::Lsb(node.pipe_address(4)[0]), true) ),
+                Some( Pipe::dynamic(PipeAddress::Lsb(node.pipe_address(5)[0]), true) ),
+            ],
+        )
+    }
+
+    /// Creates the network layer over a receiver configured with `Network::config`.
+    pub fn new(radio: Receiver<SPI, CS, CE, IRQ, FE>, node: Address) -> Self {
+        Self { radio, node, next: 1, relay: false, }
+    }
+
+    /// Returns the logical address of this node.
+    pub fn address(&self) -> Address {
+        self.node
+    }
+
+    /// Enables or disables relaying multicast frames to the next level of the tree.
+    pub fn relay(&mut self, relay: bool) {
+        self.relay = relay;
+    }
+
+    /// Changes the logical address of this node and reconfigures the radio.
+    pub async fn readdress(&mut self, node: Address) -> Result<(), Error<SPI::Error>> {
+        // Current radio configuration.
+        let current = *self.radio.config();
+
+        let config = Self::config(node, current.channel, current.datarate, current.gain);
+
+        self.radio.configure(config).await?;
+
+        self.node = node;
+
+        Ok(())
+    }
+
+    /// Sends a message to the given node, routing it through the tree.
+    /// Returns `true` if the first hop acknowledged the frame.
+    pub async fn send(&mut self, to: u16, kind: u8, message: &[u8]) -> Result<bool, Error<SPI::Error>> {
+        // Check the destination.
+        if Address::new(to).is_none() {
+            return Err( Error::Address );
+        }
+
+        let header = Header::new(self.node.0, to, self.id(), kind);
+
+        self.route(header, message).await
+    }
+
+    /// Sends a message to all nodes in the given level of the tree.
+    /// Multicast frames are not acknowledged.
+    pub async fn multicast(&mut self, level: u8, kind: u8, message: &[u8]) -> Result<bool, Error<SPI::Error>> {
+        // Check the level.
+        if level > 4 {
+            return Err( Error::Address );
+        }
+
+        let header = Header::new(self.node.0, Address::MULTICAST, self.id(), kind);
+
+        self.write(Address::multicast_address(level), header, message, AckPolicy::None).await
+    }
+
+    /// Receives the next frame addressed to this node.
+    /// Frames for other nodes are relayed on, in which case `None` is returned.
+    pub async fn recv(&mut self, timeout: Duration) -> Result<Option<Frame>, Error<SPI::Error>> {
+        self.recv_deadline(Instant::now() + timeout).await
+    }
+
+    /// Receives the next frame addressed to this node until the deadline.
+    /// Frames for other nodes are relayed on, in which case `None` is returned.
+    pub async fn recv_deadline(&mut self, deadline: Instant) -> Result<Option<Frame>, Error<SPI::Error>> {
+        // Read the next payload.
+        let payload = match self.radio.recv_deadline(deadline).await {
+            Ok(payload) => payload,
+            Err(Error::Timeout) => return Ok( None ),
+            Err(e) => return Err( e ),
+        };
+
+        // Parse the header.
+        let data = payload.view();
+
+        let header = match Header::parse(data) {
+            Some(header) => header,
+            _ => return Ok( None ),
+        };
+
+        let message = &data[Header::SIZE..];
+
+        // Relay frames for other nodes.
+        if header.to == Address::MULTICAST {
+            // Answer polls from nodes looking for a parent.
+            if header.kind == Header::NETWORK_POLL {
+                if self.node != Address::DEFAULT {
+                    // Stagger the answers of the nodes of the same level.
+                    Timer::after(Duration::from_millis(self.node.parent_pipe() as u64)).await;
+
+                    let reply = Header::new(self.node.0, header.from, header.id, Header::NETWORK_POLL);
+
+                    self.direct(header.from, reply, &[]).await?;
+                }
+
+                return Ok( None );
+            }
+
+            if self.relay {
+                self.write(Address::multicast_address(self.node.level() + 1), header, message, AckPolicy::None).await?;
+            }
+        } else if header.to != self.node.0 {
+            self.route(header, message).await?;
+
+            return Ok( None );
+        }
+
+        // Relay the address requests to the master and its responses to the requester.
+        match header.kind {
+            Header::NETWORK_REQ_ADDRESS if self.node != Address::MASTER => {
+                let request = Header { from: self.node.0, to: Address::MASTER.0, ..header };
+
+                self.route(request, message).await?;
+
+                return Ok( None );
+            },
+
+            Header::NETWORK_ADDR_RESPONSE if self.node != Address::DEFAULT => {
+                let response = Header { to: Address::DEFAULT.0, ..header };
+
+                self.direct(Address::DEFAULT.0, response, message).await?;
+
+                return Ok( None );
+            },
+
+            _ => (),
+        }
+
+        // Drop network control frames.
+        match header.kind {
+            Header::NETWORK_ACK | Header::NETWORK_PING => return Ok( None ),
+            _ => (),
+        }
+
+        // Build the frame.
+        let mut frame = Frame { header, len: message.len(), data: [0; 24], };
+        frame.data[..message.len()].copy_from_slice(message);
+
+        Ok( Some( frame ) )
+    }
+
+    /// Sends a frame straight to the multicast pipe of the given node, bypassing the tree.
+    pub(crate) async fn direct(&mut self, node: u16, header: Header, message: &[u8]) -> Result<bool, Error<SPI::Error>> {
+        self.write(Address(node).pipe_address(0), header, message, AckPolicy::None).await
+    }
+
+    /// Routes a frame to the next hop towards its destination.
+    pub(crate) async fn route(&mut self, header: Header, message: &[u8]) -> Result<bool, Error<SPI::Error>> {
+        let (hop, pipe) = self.hop(header.to);
+
+        let sent = self.write(hop.pipe_address(pipe), header, message, AckPolicy::Required).await?;
+
+        // The final hop acknowledges the frame to the sender.
+        if sent && (hop.0 == header.to) && (header.from != self.node.0) && header.acknowledged() {
+            let ack = Header::new(self.node.0, header.from, header.id, Header::NETWORK_ACK);
+
+            let (hop, pipe) = self.hop(ack.to);
+
+            self.write(hop.pipe_address(pipe), ack, &[], AckPolicy::Required).await?;
+        }
+
+        Ok( sent )
+    }
+
+    /// Returns the next hop towards the given node and the pipe to reach it.
+    fn hop(&self, to: u16) -> (Address, u8) {
+        if self.node.is_descendant(to) {
+            (Address( self.node.route_to(to) ), 5)
+        } else {
+            (self.node.parent(), self.node.parent_pipe())
+        }
+    }
+
+    /// Writes a frame to the given radio address.
+    /// Frames to multicast pipes must not require an acknowledge, as they do not send one.
+    pub(crate) async fn write(&mut self, address: [u8; 5], header: Header, message: &[u8], ack: AckPolicy) -> Result<bool, Error<SPI::Error>> {
+        // Check the length.
+        if message.len() > Self::MAXMESSAGE {
+            return Err( Error::Length );
+        }
+
+        // Build the frame.
+        let mut buffer = [0; 32];
+        buffer[..Header::SIZE].copy_from_slice(&header.bytes());
+        buffer[Header::SIZE..Header::SIZE + message.len()].copy_from_slice(message);
+
+        self.radio.send(address, &buffer[..Header::SIZE + message.len()], ack).await.map_err(Error::Spi)
+    }
+
+    /// Returns the sequence number of the next frame.
+    pub(crate) fn id(&mut self) -> u16 {
+        let id = self.next;
+        self.next = self.next.wrapping_add(1);
+
+        id
+    }
+}
+
+
+
+#[derive(Clone)]
+pub struct Frame {
+    /// Header of the frame.
+    pub header: Header,
+
+    /// Length of the message.
+    len: usize,
+
+    /// Raw message.
+    data: [u8; 24],
+}
+
+impl Frame {
+    /// Returns a view into the message.
+    pub fn view(&self) -> &[u8] {
+        &self.data[0..self.len]
+    }
+}
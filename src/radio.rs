@@ -0,0 +1,332 @@
+//! Shared radio driven by a background task.
+//! A `Runner` owns the receiver and services the IRQ, while cloneable `RadioHandle`s
+//! send requests to it through one channel and get the received packets through another.
+//! `E` is the error type of the SPI bus, so the handles get the errors of the runner as they are.
+
+
+
+use embassy_futures::select::{
+    select, Either,
+};
+
+use embassy_sync::{
+    blocking_mutex::raw::RawMutex,
+    channel::Channel,
+    mutex::Mutex,
+};
+
+use embedded_hal::{
+    digital::v2::{
+        OutputPin,
+    },
+};
+
+use embedded_hal_async::{
+    digital::{
+        Wait,
+    },
+    spi::{
+        SpiBus, SpiBusWrite,
+    },
+};
+
+use crate::{
+    common::{
+        AckPolicy, Error,
+    },
+    frontend::FrontEnd,
+    rx::{
+        Config, Payload, Pipes, Receiver,
+    },
+};
+
+
+
+/// Request from a handle to the runner.
+#[derive(Clone, Copy)]
+enum Request {
+    /// Transmit a payload to the given address.
+    Send([u8; 5], [u8; 32], usize, AckPolicy),
+
+    /// Write a new configuration.
+    Configure(Config),
+
+    /// Listen (`true`) or power down (`false`).
+    Power(bool),
+}
+
+/// Outcome of a request.
+enum Response<E> {
+    /// The payload was sent (`true`) or not acknowledged (`false`).
+    Sent(bool),
+
+    /// The request succeeded.
+    Done,
+
+    /// The request failed.
+    Failed(Error<E>),
+}
+
+
+
+pub struct Radio<M: RawMutex, E, const N: usize> {
+    /// Requests of the handles, tagged with their ID.
+    requests: Channel<M, (u32, Request), 1>,
+
+    /// Outcome of the last request, tagged with its ID.
+    responses: Channel<M, (u32, Response<E>), 1>,
+
+    /// Received packets.
+    packets: Channel<M, Payload, N>,
+
+    /// Held by a handle until its request is answered, with the ID of the next request.
+    lock: Mutex<M, u32>,
+}
+
+impl<M: RawMutex, E, const N: usize> Radio<M, E, N> {
+    /// Creates the channels, usually in a `static`.
+    pub const fn new() -> Self {
+        Self {
+            requests: Channel::new(),
+            responses: Channel::new(),
+            packets: Channel::new(),
+            lock: Mutex::new(0),
+        }
+    }
+
+    /// Returns a new handle to the radio.
+    pub fn handle(&self) -> RadioHandle<'_, M, E, N> {
+        RadioHandle { radio: self }
+    }
+}
+
+impl<M: RawMutex, E, const N: usize> Default for Radio<M, E, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+
+
+pub struct Runner<'a, SPI: SpiBus + SpiBusWrite, CS: OutputPin, CE: OutputPin, IRQ: Wait, FE: FrontEnd, M: RawMutex, const N: usize> {
+    /// The receiver serviced.
+    radio: Receiver<SPI, CS, CE, IRQ, FE>,
+
+    /// Channels shared with the handles.
+    shared: &'a Radio<M, SPI::Error, N>,
+
+    /// Queues of the pipes, if the payloads are routed by pipe.
+    pipes: Option<&'a Pipes<M, N>>,
+
+    /// `true` while the device is powered up and listening.
+    listening: bool,
+
+    /// Number of packets dropped because their queue was full.
+    dropped: u32,
+}
+
+impl<'a, SPI: SpiBus + SpiBusWrite, CS: OutputPin, CE: OutputPin, IRQ: Wait, FE: FrontEnd, M: RawMutex, const N: usize> Runner<'a, SPI, CS, CE, IRQ, FE, M, N> {
+    /// Creates the runner of the shared radio.
+    pub fn new(radio: Receiver<SPI, CS, CE, IRQ, FE>, shared: &'a Radio<M, SPI::Error, N>) -> Self {
+        Self { radio, shared, pipes: None, listening: false, dropped: 0, }
+    }
+
+    /// Routes the packets into the queue of their pipe instead of the queue of the handles.
+    pub fn route(mut self, pipes: &'a Pipes<M, N>) -> Self {
+        self.pipes = Some( pipes );
+        self
+    }
+
+    /// Stops the runner and returns the receiver.
+    pub fn release(mut self) -> Receiver<SPI, CS, CE, IRQ, FE> {
+        self.radio.stop();
+        self.radio
+    }
+
+    /// Returns the number of packets dropped because their queue was full.
+    pub fn dropped(&self) -> u32 {
+        self.dropped
+    }
+
+    /// Listens for packets and serves the requests of the handles.
+    /// Only returns on a bus error while receiving.
+    pub async fn run(&mut self) -> Result<(), SPI::Error> {
+        self.radio.listen().await?;
+        self.listening = true;
+
+        loop {
+            // Only requests can wake up a powered down device.
+            if !self.listening {
+                let request = self.shared.requests.receive().await;
+
+                self.answer(request).await;
+
+                continue;
+            }
+
+            // Only the wait on the pin is raced, so no bus transaction is cut short.
+            match select(self.shared.requests.receive(), self.radio.base.pending()).await {
+                Either::First(request) => {
+                    self.answer(request).await;
+
+                    // Packets received during a transmission do not raise the IRQ again.
+                    if self.listening {
+                        self.drain().await?;
+                    }
+                },
+
+                Either::Second(()) => {
+                    if self.radio.base.events().await?.rxready() {
+                        self.drain().await?;
+                    }
+                },
+            }
+        }
+    }
+
+    /// Serves a request of a handle and answers it.
+    /// A response left by a handle that stopped waiting is dropped, so the runner never blocks.
+    async fn answer(&mut self, (id, request): (u32, Request)) {
+        let response = self.serve(request).await;
+
+        while self.shared.responses.try_receive().is_ok() {}
+
+        // Cannot fail, as only the runner sends responses and the slot was just emptied.
+        let _ = self.shared.responses.try_send( (id, response) );
+    }
+
+    /// Serves a request of a handle.
+    async fn serve(&mut self, request: Request) -> Response<SPI::Error> {
+        let outcome = match request {
+            Request::Send(address, data, len, ack) => {
+                return match self.radio.send(address, &data[..len], ack).await {
+                    Ok(sent) => Response::Sent(sent),
+                    Err(e) => Response::Failed( Error::Spi(e) ),
+                };
+            },
+
+            Request::Configure(config) => self.radio.configure(config).await,
+
+            Request::Power(true) => {
+                let outcome = self.radio.listen().await.map_err(Error::Spi);
+
+                if outcome.is_ok() {
+                    self.listening = true;
+                }
+
+                outcome
+            },
+
+            Request::Power(false) => {
+                self.listening = false;
+
+                self.radio.powerdown().await.map(|_| ()).map_err(Error::Spi)
+            },
+        };
+
+        match outcome {
+            Ok(_) => Response::Done,
+            Err(e) => Response::Failed( e ),
+        }
+    }
+
+    /// Reads all the packets in the RX FIFO into their queue.
+    /// Packets are dropped if the queue is full.
+    async fn drain(&mut self) -> Result<(), SPI::Error> {
+        while let Some(payload) = self.radio.try_recv().await? {
+            let queue = match (self.pipes, payload.status.pipe()) {
+                (Some(pipes), Some(n)) => &pipes.queues[n as usize],
+                _ => &self.shared.packets,
+            };
+
+            if queue.try_send(payload).is_err() {
+                self.dropped += 1;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+
+
+pub struct RadioHandle<'a, M: RawMutex, E, const N: usize> {
+    /// The shared radio.
+    radio: &'a Radio<M, E, N>,
+}
+
+impl<'a, M: RawMutex, E, const N: usize> Clone for RadioHandle<'a, M, E, N> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, M: RawMutex, E, const N: usize> Copy for RadioHandle<'a, M, E, N> {}
+
+impl<'a, M: RawMutex, E, const N: usize> RadioHandle<'a, M, E, N> {
+    /// Transmits a payload to the given address and waits for the outcome.
+    /// Returns `true` if the payload was sent (and acknowledged, if the policy requires it and
+    /// the peer uses auto-acknowledge).
+    pub async fn send(&self, address: [u8; 5], data: &[u8], ack: AckPolicy) -> Result<bool, Error<E>> {
+        if data.len() > 32 {
+            return Err( Error::Length );
+        }
+
+        let mut buffer = [0u8; 32];
+        buffer[..data.len()].copy_from_slice(data);
+
+        match self.request( Request::Send(address, buffer, data.len(), ack) ).await {
+            Response::Sent(sent) => Ok( sent ),
+            Response::Failed(e) => Err( e ),
+            Response::Done => Ok( true ),
+        }
+    }
+
+    /// Writes a new configuration to the device.
+    pub async fn configure(&self, config: Config) -> Result<(), Error<E>> {
+        self.done( Request::Configure(config) ).await
+    }
+
+    /// Powers the device up and listens (`true`) or powers it down (`false`).
+    pub async fn power(&self, on: bool) -> Result<(), Error<E>> {
+        self.done( Request::Power(on) ).await
+    }
+
+    /// Waits for the next received packet.
+    /// Each packet is delivered to only one of the handles waiting.
+    pub async fn recv(&self) -> Payload {
+        self.radio.packets.receive().await
+    }
+
+    /// Returns the next received packet if there is one.
+    pub fn try_recv(&self) -> Option<Payload> {
+        self.radio.packets.try_receive().ok()
+    }
+
+    /// Sends a request that only succeeds or fails.
+    async fn done(&self, request: Request) -> Result<(), Error<E>> {
+        match self.request(request).await {
+            Response::Failed(e) => Err( e ),
+            _ => Ok(()),
+        }
+    }
+
+    /// Sends a request to the runner and waits for its response.
+    /// Other handles wait until the response arrives. If the future is dropped, the request
+    /// may still be carried out, and its response is discarded by the next request.
+    async fn request(&self, request: Request) -> Response<E> {
+        let mut next = self.radio.lock.lock().await;
+
+        let id = *next;
+        *next = next.wrapping_add(1);
+
+        self.radio.requests.send( (id, request) ).await;
+
+        loop {
+            match self.radio.responses.receive().await {
+                (n, response) if n == id => return response,
+                _ => continue,
+            }
+        }
+    }
+}
@@ -0,0 +1,321 @@
+//! Validated builder of the receiver configuration.
+//! All the methods are `const`, so a configuration built in a `const` item with `unwrap` fails
+//! to compile if it is invalid. At runtime `build` returns the list of problems found.
+
+
+
+use crate::common::{
+    AddressWidth, CRCBytes, Channel, ChipVariant,
+    Gain, DataRate, IrqEvents,
+};
+
+use super::{
+    Config, Pipe,
+};
+
+
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ConfigError {
+    /// The LSB of the pipe address is the same as the one of an earlier pipe sharing its MSBs.
+    Collision(u8),
+
+    /// Pipe 0 has only the LSB of its address, but it does not share the MSBs of the other pipes.
+    Address,
+
+    /// The full address of the pipe does not share the MSBs of the first full address of pipes 1 to 5.
+    Prefix(u8),
+
+    /// The static width of the pipe is 0 or over 32 bytes.
+    Width(u8),
+
+    /// The pipe has auto-acknowledge with CRC disabled, but the device forces CRC on.
+    AutoAck(u8),
+
+    /// The data rate is not supported by the device.
+    DataRate,
+
+    /// The 2 byte address width is only used by the sniffer.
+    AddressWidth,
+}
+
+impl ConfigError {
+    /// Returns a description of the error.
+    pub const fn message(&self) -> &'static str {
+        match self {
+            ConfigError::Collision(_) => "pipe address collides with another pipe",
+            ConfigError::Address => "pipe 0 needs a full address",
+            ConfigError::Prefix(_) => "pipes 1 to 5 must share the address MSBs",
+            ConfigError::Width(_) => "pipe width must be between 1 and 32 bytes",
+            ConfigError::AutoAck(_) => "auto-acknowledge requires CRC",
+            ConfigError::DataRate => "data rate not supported by the device",
+            ConfigError::AddressWidth => "2 byte addresses are only for the sniffer",
+        }
+    }
+}
+
+
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ConfigErrors {
+    /// Errors found.
+    errors: [Option<ConfigError>; Self::MAX],
+
+    /// Number of errors found.
+    len: usize,
+}
+
+impl ConfigErrors {
+    /// Maximum number of errors of a configuration.
+    /// Collisions and prefixes of pipes 2 to 5, the address of pipe 0, widths and auto-acknowledge
+    /// of every pipe, the data rate and the address width.
+    pub const MAX: usize = 23;
+
+    /// Creates an empty list.
+    pub const fn new() -> Self {
+        ConfigErrors { errors: [None; Self::MAX], len: 0 }
+    }
+
+    /// Adds an error to the list.
+    const fn push(&mut self, error: ConfigError) {
+        if self.len < Self::MAX {
+            self.errors[self.len] = Some( error );
+            self.len += 1;
+        }
+    }
+
+    /// Returns the error at the given index.
+    pub const fn get(&self, i: usize) -> Option<ConfigError> {
+        match i < self.len {
+            true => self.errors[i],
+            _ => None,
+        }
+    }
+
+    /// Returns the number of errors.
+    pub const fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if the configuration is valid.
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Iterates over the errors.
+    pub fn iter(&self) -> impl Iterator<Item = ConfigError> + '_ {
+        self.errors[..self.len].iter().flatten().copied()
+    }
+}
+
+impl Default for ConfigErrors {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+
+
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub struct ConfigBuilder {
+    /// Configuration being built.
+    config: Config,
+
+    /// Variant of the device, if known.
+    chip: Option<ChipVariant>,
+}
+
+impl ConfigBuilder {
+    /// Creates a builder on channel 2 at 1 Mbps and maximum gain, with 5 byte addresses,
+    /// 2 byte CRC, all the interrupts and no pipes enabled.
+    /// Star topologies give each sender its own pipe, with full addresses sharing the MSBs.
+    pub const fn new() -> Self {
+        ConfigBuilder {
+            config: Config {
+                channel: Channel::saturating(2),
+                gain: Gain::Max,
+                datarate: DataRate::High,
+                crc: Some( CRCBytes::TwoBytes ),
+                addrwidth: AddressWidth::FiveBytes,
+                pipes: [None; 6],
+                irq: IrqEvents::ALL,
+            },
+            chip: None,
+        }
+    }
+
+    /// Sets the RF channel.
+    pub const fn channel(mut self, channel: Channel) -> Self {
+        self.config.channel = channel;
+        self
+    }
+
+    /// Sets the data rate.
+    pub const fn datarate(mut self, datarate: DataRate) -> Self {
+        self.config.datarate = datarate;
+        self
+    }
+
+    /// Sets the gain.
+    pub const fn gain(mut self, gain: Gain) -> Self {
+        self.config.gain = gain;
+        self
+    }
+
+    /// Sets the CRC, or disables it with `None`.
+    pub const fn crc(mut self, crc: Option<CRCBytes>) -> Self {
+        self.config.crc = crc;
+        self
+    }
+
+    /// Sets the address width.
+    pub const fn addrwidth(mut self, addrwidth: AddressWidth) -> Self {
+        self.config.addrwidth = addrwidth;
+        self
+    }
+
+    /// Sets the events that drive the IRQ pin.
+    pub const fn irq(mut self, irq: IrqEvents) -> Self {
+        self.config.irq = irq;
+        self
+    }
+
+    /// Enables the pipe with the given configuration.
+    /// Indices over 5 are ignored.
+    pub const fn pipe(mut self, n: usize, pipe: Pipe) -> Self {
+        if n < 6 {
+            self.config.pipes[n] = Some( pipe );
+        }
+
+        self
+    }
+
+    /// Sets the variant of the device, to check the features it supports.
+    /// Without it the data rate is not checked, and `Receiver::configure` fails with
+    /// `Unsupported` if the device lacks it.
+    pub const fn chip(mut self, chip: ChipVariant) -> Self {
+        self.chip = Some( chip );
+        self
+    }
+
+    /// Returns the problems of the configuration.
+    pub const fn validate(&self) -> ConfigErrors {
+        let mut errors = ConfigErrors::new();
+
+        let pipes = &self.config.pipes;
+
+        // Pipes 1 to 5 share the MSBs, so the LSBs of their addresses must differ.
+        let mut i = 2;
+
+        while i < 6 {
+            if let Some(pipe) = pipes[i] {
+                let mut j = 1;
+
+                while j < i {
+                    if let Some(other) = pipes[j] {
+                        if other.address.lsb() == pipe.address.lsb() {
+                            errors.push(ConfigError::Collision(i as u8));
+                            break;
+                        }
+                    }
+
+                    j += 1;
+                }
+            }
+
+            i += 1;
+        }
+
+        // Pipe 0 does not share the MSBs, so it needs a full address.
+        if let Some(pipe) = pipes[0] {
+            if pipe.address.msb().is_none() {
+                errors.push(ConfigError::Address);
+            }
+        }
+
+        // Full addresses of pipes 1 to 5 must match the shared MSBs within the address width.
+        let prefix = self.config.prefix();
+        let len = self.config.addrwidth.bytes() - 1;
+
+        let mut i = 1;
+
+        while i < 6 {
+            if let Some(pipe) = pipes[i] {
+                if let Some(msb) = pipe.address.msb() {
+                    let mut j = 0;
+
+                    while j < len {
+                        if msb[j] != prefix[j] {
+                            errors.push(ConfigError::Prefix(i as u8));
+                            break;
+                        }
+
+                        j += 1;
+                    }
+                }
+            }
+
+            i += 1;
+        }
+
+        // Check the width and auto-acknowledge of every pipe.
+        let mut i = 0;
+
+        while i < 6 {
+            if let Some(pipe) = pipes[i] {
+                if let Some(w) = pipe.width {
+                    if (w == 0) || (w > 32) {
+                        errors.push(ConfigError::Width(i as u8));
+                    }
+                }
+
+                if pipe.autoack && self.config.crc.is_none() {
+                    errors.push(ConfigError::AutoAck(i as u8));
+                }
+            }
+
+            i += 1;
+        }
+
+        // The device only supports 2 byte addresses as a quirk used by the sniffer, which has
+        // its own configuration.
+        if matches!(self.config.addrwidth, AddressWidth::TwoBytes) {
+            errors.push(ConfigError::AddressWidth);
+        }
+
+        // Check the features of the device, if known.
+        if let Some(chip) = self.chip {
+            if matches!(self.config.datarate, DataRate::Low) && !chip.lowrate() {
+                errors.push(ConfigError::DataRate);
+            }
+        }
+
+        errors
+    }
+
+    /// Builds the configuration.
+    /// Returns the list of problems if it is not valid.
+    pub const fn build(self) -> Result<Config, ConfigErrors> {
+        let errors = self.validate();
+
+        match errors.is_empty() {
+            true => Ok( self.config ),
+            _ => Err( errors ),
+        }
+    }
+
+    /// Builds the configuration.
+    /// Panics with the first problem if it is not valid, which fails the compilation in a `const` item.
+    pub const fn unwrap(self) -> Config {
+        match self.validate().get(0) {
+            Some(error) => panic!("{}", error.message()),
+            _ => self.config,
+        }
+    }
+}
+
+impl Default for ConfigBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -3,18 +3,22 @@
 
 
 use crate::common::{
-    AddressWidth, CRCBytes,
-    Gain, DataRate,
+    AddressWidth, Channel, ChipVariant, CRCBytes,
+    Gain, DataRate, IrqEvents, Region, TxPower,
 };
 
-use super::Pipe;
+use crate::esb::Preamble;
+
+use super::{
+    Pipe, PipeAddress,
+};
 
 
 
-#[derive(Clone, Copy, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub struct Config {
     /// RF Channel.
-    pub channel: u8,
+    pub channel: Channel,
 
     /// Antenna gain.
     pub gain: Gain,
@@ -28,28 +32,96 @@ pub struct Config {
     /// Address Width.
     pub addrwidth: AddressWidth,
 
-    /// Main address.
-    pub address: [u8; 5],
-
-    /// Common sub address.
-    pub subaddress: [u8; 4],
-
     /// Pipe configuration.
     pub pipes: [Option<Pipe>; 6],
+
+    /// Events that drive the IRQ pin.
+    /// The others are still flagged in STATUS.
+    pub irq: IrqEvents,
 }
 
 impl Config {
+    /// Reset value of the bytes of the pipe 0 address.
+    pub const PRIMARY: u8 = 0xE7;
+
+    /// Reset value of the bytes of the pipe 1 address.
+    pub const PREFIX: u8 = 0xC2;
+
     /// Static initializer for a full struct.
-    pub const fn configured(channel: u8, datarate: DataRate, gain: Gain, addrwidth: AddressWidth, crc: Option<CRCBytes>, address: [u8; 5], subaddress: [u8; 4], pipes: [Option<Pipe>; 6],) -> Self {
+    pub const fn configured(channel: Channel, datarate: DataRate, gain: Gain, addrwidth: AddressWidth, crc: Option<CRCBytes>, pipes: [Option<Pipe>; 6],) -> Self {
         Config {
             channel,
             datarate,
             gain,
             addrwidth,
             crc,
-            address,
-            subaddress,
             pipes,
+            irq: IrqEvents::ALL,
+        }
+    }
+
+    /// Configuration of the promiscuous sniffer.
+    /// Listens on pipe 0 with a 2 byte address ending in the preamble, without CRC nor
+    /// auto-acknowledge, and captures 32 raw bytes per frame.
+    pub const fn sniffer(channel: Channel, datarate: DataRate, preamble: Preamble) -> Self {
+        Config {
+            channel,
+            datarate,
+            gain: Gain::Max,
+            addrwidth: AddressWidth::TwoBytes,
+            crc: None,
+            pipes: [Some( Pipe::sized(PipeAddress::Full(preamble.address()), false, 32) ), None, None, None, None, None],
+            irq: IrqEvents::ALL,
+        }
+    }
+
+    /// Sets the level of the device closest to the target output power in dBm, given the
+    /// external PA gain of the board and the antenna gain in dBi.
+    /// Only levels under the EIRP limit of the region are picked.
+    /// Returns `None` and keeps the gain if no level is under the limit.
+    pub fn txpower(&mut self, chip: ChipVariant, target: i8, offset: i8, antenna: i8, region: Region) -> Option<TxPower> {
+        // Highest level allowed in the region.
+        let limit = Gain::ALL.iter().rev().map(|g| TxPower::new(chip, *g, offset)).find(|p| p.eirp(antenna) <= region.max_eirp())?;
+
+        let power = match TxPower::closest(chip, target, offset) {
+            power if power.dbm() > limit.dbm() => limit,
+            power => power,
+        };
+
+        self.gain = power.gain;
+
+        Some( power )
+    }
+
+    /// Returns the MSBs shared by pipes 1 to 5, LSB first.
+    /// Taken from the first of them with a full address, or the reset value of the device.
+    pub const fn prefix(&self) -> [u8; 4] {
+        let mut i = 1;
+
+        while i < 6 {
+            if let Some(pipe) = self.pipes[i] {
+                if let Some(msb) = pipe.address.msb() {
+                    return msb;
+                }
+            }
+
+            i += 1;
+        }
+
+        [Self::PREFIX; 4]
+    }
+
+    /// Returns the full address of the pipe, LSB first.
+    /// Returns `None` if the pipe is disabled.
+    pub const fn rxaddress(&self, n: usize) -> Option<[u8; 5]> {
+        if n > 5 {
+            return None;
+        }
+
+        match self.pipes[n] {
+            Some(pipe) if n == 0 => Some( pipe.address.full([Self::PRIMARY; 4]) ),
+            Some(pipe) => Some( pipe.address.full(self.prefix()) ),
+            _ => None,
         }
     }
 
@@ -58,8 +130,11 @@ impl Config {
         // Register word.
         let mut word = 0;
 
-        // Mask the MAX RT interrupt, set PRX.
-        word |= (1 << 4) | 1;
+        // Set PRX.
+        word |= 1;
+
+        // Mask the events that do not drive the IRQ pin.
+        word |= IrqEvents::ALL.bits() & !self.irq.bits();
 
         // Configure the CRC.
         if let Some(crc) = self.crc {
@@ -106,22 +181,27 @@ impl Config {
             AddressWidth::FiveBytes  => 0b11,
             AddressWidth::FourBytes  => 0b10,
             AddressWidth::ThreeBytes => 0b01,
+            AddressWidth::TwoBytes   => 0b00,
         }
     }
 
-    /// Creates the secondary address.
+    /// Creates the primary address, of pipe 0.
+    pub(crate) const fn primary(&self) -> [u8; 5] {
+        match self.rxaddress(0) {
+            Some(address) => address,
+            _ => [Self::PRIMARY; 5],
+        }
+    }
+
+    /// Creates the secondary address, of pipe 1.
+    /// Written even if pipe 1 is disabled, as pipes 2 to 5 take the MSBs from it.
     pub(crate) const fn secondary(&self) -> [u8; 5] {
-        [
-            self.subaddress[0],
-            self.subaddress[1],
-            self.subaddress[2],
-            self.subaddress[3],
-
-            match self.pipes[1] {
-                Some(pipe) => pipe.sub,
-                _ => 0,
-            }
-        ]
+        let msb = self.prefix();
+
+        match self.pipes[1] {
+            Some(pipe) => pipe.address.full(msb),
+            _ => [Self::PREFIX, msb[0], msb[1], msb[2], msb[3]],
+        }
     }
 
     /// Creates the pipe configuration.
@@ -159,7 +239,7 @@ impl Config {
                 }
 
                 // Set address.
-                if i > 1 { addr[i - 2] = pipe.sub }
+                if i > 1 { addr[i - 2] = pipe.address.lsb() }
             }
 
             // Increase the index.
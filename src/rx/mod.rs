@@ -2,18 +2,24 @@
 
 
 
+mod builder;
 mod config;
 mod pipe;
+mod split;
+mod stream;
 
 
 
+pub use builder::{ ConfigBuilder, ConfigError, ConfigErrors };
 pub use config::Config;
-pub use pipe::Pipe;
+pub use pipe::{ Pipe, PipeAddress };
+pub use split::{ PipeReceiver, Pipes };
+pub use stream::StreamReport;
 
 
 
 use embassy_time::{
-    Duration, Timer,
+    Duration, Instant, Timer,
 };
 
 use embedded_hal::{
@@ -36,38 +42,94 @@ use super::{
         BaseDriver,
     },
     common::{
-        BlockRegister, Command, State, Register, Status,
+        AckPolicy, AddressWidth, BlockRegister, Channel, ChipVariant, CRCBytes, Command, DataRate, Error, IrqEvents, State, Register, Status,
+    },
+    esb::{
+        Preamble, Sniffed,
+    },
+    frontend::{
+        FrontEnd, NoFrontEnd,
+    },
+    test_mode::{
+        STARTUP,
     },
 };
 
 
-pub struct Receiver<SPI: SpiBus + SpiBusWrite, CS: OutputPin, CE: OutputPin, IRQ: Wait> {
+pub struct Receiver<SPI: SpiBus + SpiBusWrite, CS: OutputPin, CE: OutputPin, IRQ: Wait, FE: FrontEnd = NoFrontEnd> {
     /// Basic device driver.
-    pub base: BaseDriver<SPI, CS, CE, IRQ>,
+    pub base: BaseDriver<SPI, CS, CE, IRQ, FE>,
 
     /// Configuration of the receiver.
     config: Config,
 
+    /// Variant of the device.
+    chip: ChipVariant,
+
     /// Power State of the device.
     state: State,
+
+    /// How the device listens between payloads.
+    mode: ListenMode,
 }
 
-impl<SPI: SpiBus + SpiBusWrite, CS: OutputPin, CE: OutputPin, IRQ: Wait> Receiver<SPI, CS, CE, IRQ> {
+impl<SPI: SpiBus + SpiBusWrite, CS: OutputPin, CE: OutputPin, IRQ: Wait, FE: FrontEnd> Receiver<SPI, CS, CE, IRQ, FE> {
     /// Creates a new receiver with the given configuration.
-    pub async fn new(base: BaseDriver<SPI, CS, CE, IRQ>, config: Config) -> Result<Self, SPI::Error> {
+    /// Detects the variant of the device first.
+    pub async fn new(mut base: BaseDriver<SPI, CS, CE, IRQ, FE>, config: Config) -> Result<Self, Error<SPI::Error>> {
+        let chip = base.detect().await.map_err(Error::Spi)?;
+
+        // Create the device.
+        let mut device = Self { base, config, chip, state: State::PowerDown, mode: ListenMode::Toggled, };
+
+        // Write the configuration.
+        device.configure(config).await?;
+
+        Ok( device )
+    }
+
+    /// Returns the configuration of the receiver.
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Returns the variant of the device.
+    pub fn chip(&self) -> ChipVariant {
+        self.chip
+    }
+
+    /// Sets how the device listens between payloads.
+    pub fn mode(&mut self, mode: ListenMode) {
+        self.mode = mode;
+    }
+
+    /// Writes a new configuration to the device.
+    /// The device returns to its previous state afterwards.
+    /// Fails with `Unsupported` if the variant lacks the data rate.
+    pub async fn configure(&mut self, config: Config) -> Result<(), Error<SPI::Error>> {
+        if (config.datarate == DataRate::Low) && !self.chip.lowrate() {
+            return Err( Error::Unsupported );
+        }
+
         // Configure the pipes.
         let (rxautoack, rxenable, dynpd, width, addr) = config.pipeconfig();
 
+        // Keep the device powered up.
+        let power = match self.state {
+            State::PowerDown => 0,
+            _ => 1 << 1,
+        };
+
         // Registers to write.
         let registers = [
-            (Register::Config        , config.config()   ), // 0
-            (Register::AutoAck       , rxautoack         ), // 1
-            (Register::RXEnable      , rxenable          ), // 2
-            (Register::AddressWidth  , config.addrwidth()), // 3
-            (Register::RFChannel     , config.channel    ), // 4
-            (Register::RFSetup       , config.rfsetup()  ), // 5
-            (Register::DynamicPayload, dynpd             ), // 6
-            (Register::Feature       , config.features() ), // 7
+            (Register::Config        , config.config() | power), // 0
+            (Register::AutoAck       , rxautoack              ), // 1
+            (Register::RXEnable      , rxenable               ), // 2
+            (Register::AddressWidth  , config.addrwidth()     ), // 3
+            (Register::RFChannel     , config.channel.raw()   ), // 4
+            (Register::RFSetup       , self.chip.rfsetup(config.rfsetup())), // 5
+            (Register::DynamicPayload, dynpd                  ), // 6
+            (Register::Feature       , config.features()      ), // 7
 
             (Register::RX0Width      , width[0]), // 8
             (Register::RX1Width      , width[1]), // 9
@@ -84,34 +146,41 @@ impl<SPI: SpiBus + SpiBusWrite, CS: OutputPin, CE: OutputPin, IRQ: Wait> Receive
 
         // Register block.
         let blocks = [
-            (BlockRegister::RX0Address, config.address),
+            (BlockRegister::RX0Address, config.primary()),
             (BlockRegister::RX1Address, config.secondary()),
         ];
 
-        // Create the device.
-        let mut device = Self { base, config, state: State::PowerDown, };
+        // Stop listening while the registers are written.
+        self.base.disable();
 
         // Write all the registers.
         for (r, v) in registers.iter() {
-            device.base.writereg(*r, *v).await?;
+            self.base.writereg(*r, *v).await.map_err(Error::Spi)?;
         }
 
         // Write the two register blocks.
         for (r, data) in blocks.iter() {
-            device.base.writeblock(*r, data).await?;
+            self.base.writeblock(*r, data).await.map_err(Error::Spi)?;
         }
 
+        // Store the new configuration.
+        self.config = config;
 
-        Ok( device )
+        // Resume listening.
+        if self.state == State::Listening {
+            self.base.enable();
+        }
+
+        Ok(())
     }
 
     /// Sets the RF channel used.
-    pub async fn rfchannel(&mut self, channel: u8) -> Result<Status, SPI::Error> {
+    pub async fn rfchannel(&mut self, channel: Channel) -> Result<Status, SPI::Error> {
         // Change the channel in the config.
         self.config.channel = channel;
 
         // Write the change to the device.
-        self.base.writereg(Register::RFChannel, channel).await
+        self.base.writereg(Register::RFChannel, channel.raw()).await
     }
 
     /// Powers up the device.
@@ -123,6 +192,21 @@ impl<SPI: SpiBus + SpiBusWrite, CS: OutputPin, CE: OutputPin, IRQ: Wait> Receive
         self.base.writereg(Register::Config, register | (1 << 1)).await
     }
 
+    /// Powers down the device.
+    /// The configuration is kept and restored on the next `listen`.
+    pub async fn powerdown(&mut self) -> Result<Status, SPI::Error> {
+        self.stop();
+
+        // Read the register.
+        let (_, register) = self.base.readreg(Register::Config).await?;
+
+        // Change state.
+        self.state = State::PowerDown;
+
+        // Write the modified register.
+        self.base.writereg(Register::Config, register & !(1 << 1)).await
+    }
+
     /// Sets the device up and starts listening for incoming packets.
     pub async fn listen(&mut self) -> Result<(), SPI::Error> {
         // Power up the device.
@@ -160,20 +244,33 @@ impl<SPI: SpiBus + SpiBusWrite, CS: OutputPin, CE: OutputPin, IRQ: Wait> Receive
         Ok(())
     }
 
-    /// Listens for a payload in one of the active pipes.
-    /// Awaits until a new packet is ready.
-    pub async fn recv(&mut self, payload: Option<()>, stop: bool, timeout: Duration) -> Result<Option<Payload>, SPI::Error> {
+    /// Returns the next payload in the RX FIFO without waiting.
+    /// Starts listening if the device was not, and keeps listening afterwards.
+    pub async fn try_recv(&mut self) -> Result<Option<Payload>, SPI::Error> {
         // Begin listening.
         self.listen().await?;
 
-        // Wait for the IRQ.
-        embassy_time::with_timeout(timeout, self.base.wait()).await;
+        // Leave CE alone while there is nothing to read, so polling does not restart RX.
+        let (_, fifo) = self.base.readreg(Register::FifoStatus).await?;
 
-        // Set CE low.
-        self.base.disable();
+        if (fifo & 1) != 0 {
+            return Ok( None );
+        }
+
+        // Set CE low, unless the device keeps receiving while the FIFO is read.
+        if self.mode == ListenMode::Toggled {
+            self.base.disable();
+        }
 
         // Read the payload.
-        let payload = match self.base.rxpayload().await? {
+        let read = self.base.rxpayload().await;
+
+        // Set CE high again.
+        if self.mode == ListenMode::Toggled {
+            self.base.enable();
+        }
+
+        let payload = match read? {
             Some((len, raw)) => {
                 // Get the status.
                 let status = Status(raw[0]);
@@ -191,21 +288,218 @@ impl<SPI: SpiBus + SpiBusWrite, CS: OutputPin, CE: OutputPin, IRQ: Wait> Receive
             _ => return Ok( None ),
         };
 
-        // Check if the device stops listening.
-        if stop {
-            self.base.disable();
-        } else {
-            // Clear interrupts and get ready for the next payload.
-            self.base.writereg(Register::Status, 0b111 << 4).await?;
-        }
+        // Clear the RX interrupt to get the IRQ of the next payload.
+        self.base.writereg(Register::Status, IrqEvents::RX_READY.bits()).await?;
 
         Ok( Some( payload ) )
     }
 
+    /// Waits for a payload in one of the active pipes.
+    /// Keeps listening afterwards.
+    pub async fn recv(&mut self) -> Result<Payload, SPI::Error> {
+        loop {
+            if let Some(payload) = self.try_recv().await? {
+                return Ok( payload );
+            }
+
+            self.base.wait().await?;
+        }
+    }
+
+    /// Waits for a payload in one of the active pipes until the deadline.
+    /// Fails with `Timeout` if none arrived in time. Keeps listening afterwards.
+    pub async fn recv_deadline(&mut self, deadline: Instant) -> Result<Payload, Error<SPI::Error>> {
+        loop {
+            if let Some(payload) = self.try_recv().await.map_err(Error::Spi)? {
+                return Ok( payload );
+            }
+
+            let remaining = deadline.saturating_duration_since(Instant::now());
+
+            // Only the wait on the pin is raced with the deadline, the events are cleared after it.
+            match embassy_time::with_timeout(remaining, self.base.pending()).await {
+                Ok(()) => { self.base.events().await.map_err(Error::Spi)?; },
+
+                // Check the FIFO once more, the payload may have arrived with the deadline.
+                _ => return self.try_recv().await.map_err(Error::Spi)?.ok_or(Error::Timeout),
+            }
+        }
+    }
+
+    /// Waits for a payload in one of the active pipes for the given time.
+    /// Fails with `Timeout` if none arrived in time. Keeps listening afterwards.
+    pub async fn recv_timeout(&mut self, timeout: Duration) -> Result<Payload, Error<SPI::Error>> {
+        self.recv_deadline(Instant::now() + timeout).await
+    }
+
+    /// Transmits a payload to the given address and waits for the outcome.
+    /// Returns `true` if the payload was sent (and acknowledged, if the policy requires it and
+    /// the peer uses auto-acknowledge).
+    /// Payloads longer than 32 bytes are truncated.
+    /// The device returns to its previous state afterwards.
+    pub async fn send(&mut self, address: [u8; 5], data: &[u8], ack: AckPolicy) -> Result<bool, SPI::Error> {
+        let previous = self.txmode(address).await?;
+
+        // Load the payload and start the transmission.
+        self.base.txpayload(data, ack).await?;
+        self.base.enable();
+
+        // Wait until the transmission finishes.
+        // A pending RX interrupt may also hold the IRQ low.
+        let events = loop {
+            let events = self.base.wait().await?;
+
+            if events.txsent() || events.maxretries() {
+                break events;
+            }
+        };
+
+        // Set CE low.
+        self.base.disable();
+
+        // Drop the payload if it was never delivered.
+        if events.maxretries() {
+            self.base.command( Command::TXFlush ).await?;
+        }
+
+        self.rxmode(previous).await?;
+
+        Ok( events.txsent() )
+    }
+
+    /// Switches the device to PTX with the given destination and an empty TX FIFO.
+    /// Returns the state to return to with `rxmode`.
+    async fn txmode(&mut self, address: [u8; 5]) -> Result<State, SPI::Error> {
+        // Stop listening while the device is reconfigured.
+        self.base.disable();
+
+        // Store the state to return to.
+        let previous = self.state;
+
+        // Power up the device.
+        match self.state {
+            State::PowerDown => {
+                // Power up and wait for the oscillator to settle.
+                self.powerup().await?;
+
+                Timer::after(STARTUP).await;
+            },
+
+            _ => (),
+        }
+
+        self.state = State::Transmitting;
+
+        // Set PTX, with the TX interrupts unmasked to get the outcome.
+        let tx = (IrqEvents::TX_SENT | IrqEvents::MAX_RETRIES).bits();
+
+        self.base.writereg(Register::Config, (self.config.config() & !1 & !tx) | (1 << 1)).await?;
+
+        // Set the destination and listen for the acknowledge in pipe 0.
+        self.base.writeblock(BlockRegister::TXAddress, &address).await?;
+        self.base.writeblock(BlockRegister::RX0Address, &address).await?;
+
+        // Clear the TX interrupts and flush the TX FIFO.
+        self.base.writereg(Register::Status, 0b11 << 4).await?;
+        self.base.command( Command::TXFlush ).await?;
+
+        Ok( previous )
+    }
+
+    /// Switches the device back to PRX and the given state after a transmission.
+    async fn rxmode(&mut self, previous: State) -> Result<(), SPI::Error> {
+        // Restore pipe 0 and PRX.
+        self.base.writeblock(BlockRegister::RX0Address, &self.config.primary()).await?;
+
+        if previous != State::PowerDown {
+            self.base.writereg(Register::Config, self.config.config() | (1 << 1)).await?;
+        }
+
+        // Return to the previous state.
+        match previous {
+            State::Listening => {
+                // Set CE high.
+                self.base.enable();
+
+                self.state = State::Listening;
+            },
+
+            State::PowerDown => {
+                // Power down again.
+                self.base.writereg(Register::Config, self.config.config() & !(1 << 1)).await?;
+
+                self.state = State::PowerDown;
+            },
+
+            _ => self.state = State::Standby,
+        }
+
+        Ok(())
+    }
+
+    /// Switches the device to the promiscuous sniffer and starts listening.
+    /// Captures the frames on the channel whose address starts with the given preamble.
+    /// Call `configure` with the previous configuration to leave the sniffer.
+    pub async fn sniffer(&mut self, channel: Channel, datarate: DataRate, preamble: Preamble) -> Result<(), Error<SPI::Error>> {
+        self.configure( Config::sniffer(channel, datarate, preamble) ).await?;
+
+        self.listen().await.map_err(Error::Spi)
+    }
+
+    /// Waits for a frame in sniffer mode and decodes it with the address width and CRC of the link.
+    /// Returns `None` on timeout or if no valid frame was found in the capture.
+    pub async fn sniff(&mut self, width: AddressWidth, crc: CRCBytes, timeout: Duration) -> Result<Option<Sniffed>, SPI::Error> {
+        let payload = match self.recv_timeout(timeout).await {
+            Ok(payload) => payload,
+            Err(Error::Spi(e)) => return Err( e ),
+            _ => return Ok( None ),
+        };
+
+        Ok( Sniffed::decode(payload.view(), width, crc) )
+    }
+
     /// Stops the device listening for packets.
-    pub fn unlisten(&mut self) {
+    /// The only way to leave RX in continuous mode.
+    pub fn stop(&mut self) {
         self.base.disable();
+
+        // Change state.
+        if self.state == State::Listening {
+            self.state = State::Standby;
+        }
     }
+
+    /// Stops the device listening for packets.
+    #[deprecated(note = "renamed to `stop`")]
+    pub fn unlisten(&mut self) {
+        self.stop()
+    }
+}
+
+
+
+/// How the device listens between payloads.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ListenMode {
+    /// CE drops while each payload is read and rises again afterwards.
+    /// Every payload costs the 130 µs RX settling time, and packets arriving meanwhile are lost.
+    Toggled,
+
+    /// CE stays high and the FIFO is read while the device keeps receiving.
+    /// Only `stop`, `send` and `configure` leave RX.
+    ///
+    /// The device stays ready to receive, so the only cost of a payload is reading it. This is
+    /// a calculated lower bound, not a measurement: 41 bytes on the bus (FIFO_STATUS twice,
+    /// R_RX_PL_WID, a 33 byte R_RX_PAYLOAD and the write to STATUS), 41 µs at an SPI clock of
+    /// 8 MHz, against the 130 µs RX settling time each payload costs in `Toggled`. The chip
+    /// select, the wake up of the task and the scheduling overhead of the HAL and executor come
+    /// on top and depend on the target.
+    ///
+    /// To measure them, toggle a spare pin right after `recv` returns and compare it on a logic
+    /// analyzer with the falling edge of IRQ, for both modes on the same MCU, SPI clock and
+    /// executor. The 3 deep RX FIFO absorbs packets arriving meanwhile, so no packet is lost as
+    /// long as the FIFO is drained within 3 air times, about 490 µs for 32 byte payloads at 2 Mbps.
+    Continuous,
 }
 
 
@@ -2,10 +2,49 @@
 
 
 
-#[derive(Clone, Copy, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PipeAddress {
+    /// Full address, LSB first.
+    /// Pipe 0 has its own address, while pipes 1 to 5 must share the MSBs.
+    Full([u8; 5]),
+
+    /// LSB of the address, with the MSBs shared by pipes 1 to 5.
+    Lsb(u8),
+}
+
+impl PipeAddress {
+    /// Returns the LSB of the address.
+    pub const fn lsb(&self) -> u8 {
+        match self {
+            PipeAddress::Full(address) => address[0],
+            PipeAddress::Lsb(lsb) => *lsb,
+        }
+    }
+
+    /// Returns the MSBs of the address, LSB first.
+    /// Returns `None` if only the LSB is known.
+    pub const fn msb(&self) -> Option<[u8; 4]> {
+        match self {
+            PipeAddress::Full(a) => Some( [a[1], a[2], a[3], a[4]] ),
+            PipeAddress::Lsb(_) => None,
+        }
+    }
+
+    /// Returns the full address, completing it with the given MSBs if needed.
+    pub const fn full(&self, msb: [u8; 4]) -> [u8; 5] {
+        match self {
+            PipeAddress::Full(address) => *address,
+            PipeAddress::Lsb(lsb) => [*lsb, msb[0], msb[1], msb[2], msb[3]],
+        }
+    }
+}
+
+
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub struct Pipe {
-    /// Pipe subaddress.
-    pub sub: u8,
+    /// Pipe address.
+    pub address: PipeAddress,
 
     /// Pipe auto-acknowledge.
     pub autoack: bool,
@@ -17,12 +56,12 @@ pub struct Pipe {
 
 impl Pipe {
     /// Configures a dynamic length pipeline.
-    pub const fn dynamic(sub: u8, autoack: bool) -> Pipe {
-        Pipe { sub, autoack, width: None }
+    pub const fn dynamic(address: PipeAddress, autoack: bool) -> Pipe {
+        Pipe { address, autoack, width: None }
     }
 
     /// Configures a set length pipeline.
-    pub const fn sized(sub: u8, autoack: bool, width: u8) -> Pipe {
-        Pipe { sub, autoack, width: Some(width) }
+    pub const fn sized(address: PipeAddress, autoack: bool, width: u8) -> Pipe {
+        Pipe { address, autoack, width: Some(width) }
     }
 }
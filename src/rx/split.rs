@@ -0,0 +1,107 @@
+//! Demultiplexed pipes of a receiver.
+//! The `Runner` of the shared radio services the IRQ and routes each payload by STATUS.RX_P_NO
+//! into a small queue per pipe, so separate tasks can own separate pipes through
+//! `PipeReceiver`s while replying through a `RadioHandle`.
+
+
+
+use embassy_sync::{
+    blocking_mutex::raw::RawMutex,
+    channel::{
+        self, Channel,
+    },
+};
+
+use embedded_hal::{
+    digital::v2::{
+        OutputPin,
+    },
+};
+
+use embedded_hal_async::{
+    digital::{
+        Wait,
+    },
+    spi::{
+        SpiBus, SpiBusWrite,
+    },
+};
+
+use crate::{
+    frontend::FrontEnd,
+    radio::{
+        Radio, RadioHandle, Runner,
+    },
+};
+
+use super::{
+    Payload, Receiver,
+};
+
+
+
+pub struct Pipes<M: RawMutex, const N: usize> {
+    /// Queue of payloads of each pipe.
+    pub(crate) queues: [Channel<M, Payload, N>; 6],
+}
+
+impl<M: RawMutex, const N: usize> Pipes<M, N> {
+    /// Creates the queues, usually in a `static`.
+    pub const fn new() -> Self {
+        Self {
+            queues: [
+                Channel::new(), Channel::new(), Channel::new(),
+                Channel::new(), Channel::new(), Channel::new(),
+            ],
+        }
+    }
+}
+
+impl<M: RawMutex, const N: usize> Default for Pipes<M, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+
+
+pub struct PipeReceiver<'a, M: RawMutex, const N: usize> {
+    /// Pipe of the payloads.
+    pipe: u8,
+
+    /// Queue of the pipe.
+    queue: channel::Receiver<'a, M, Payload, N>,
+}
+
+impl<'a, M: RawMutex, const N: usize> PipeReceiver<'a, M, N> {
+    /// Returns the pipe of the payloads.
+    pub fn pipe(&self) -> u8 {
+        self.pipe
+    }
+
+    /// Waits for the next payload of the pipe.
+    pub async fn recv(&self) -> Payload {
+        self.queue.receive().await
+    }
+
+    /// Returns the next payload of the pipe if there is one.
+    pub fn try_recv(&self) -> Option<Payload> {
+        self.queue.try_receive().ok()
+    }
+}
+
+
+
+impl<SPI: SpiBus + SpiBusWrite, CS: OutputPin, CE: OutputPin, IRQ: Wait, FE: FrontEnd> Receiver<SPI, CS, CE, IRQ, FE> {
+    /// Splits the receiver into the runner of the shared radio, a handle to it and a receiver
+    /// for each enabled pipe.
+    /// The runner must run in its own task for the receivers to get payloads.
+    #[allow(clippy::type_complexity)]
+    pub fn split<'a, M: RawMutex, const N: usize>(self, radio: &'a Radio<M, SPI::Error, N>, pipes: &'a Pipes<M, N>) -> (Runner<'a, SPI, CS, CE, IRQ, FE, M, N>, RadioHandle<'a, M, SPI::Error, N>, [Option<PipeReceiver<'a, M, N>>; 6]) {
+        let receivers = core::array::from_fn(|n| {
+            self.config().pipes[n].map(|_| PipeReceiver { pipe: n as u8, queue: pipes.queues[n].receiver() })
+        });
+
+        (Runner::new(self, radio).route(pipes), radio.handle(), receivers)
+    }
+}
@@ -0,0 +1,233 @@
+//! Streaming transmission of bulk data.
+//! The data is split in 32 byte frames that keep the 3 deep TX FIFO full, with CE held high
+//! so the device goes from one frame to the next without leaving TX.
+
+
+
+use embassy_time::{
+    Duration, Instant, Timer,
+};
+
+use embedded_hal::{
+    digital::v2::{
+        OutputPin,
+    },
+};
+
+use embedded_hal_async::{
+    digital::{
+        Wait,
+    },
+    spi::{
+        SpiBus, SpiBusWrite,
+    },
+};
+
+use crate::{
+    common::{
+        AckPolicy, ChipVariant, Command, Register,
+    },
+    frontend::FrontEnd,
+};
+
+use super::Receiver;
+
+
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct StreamReport {
+    /// Number of frames of the data.
+    pub frames: usize,
+
+    /// Number of frames dropped after running out of retries.
+    pub lost: usize,
+
+    /// Number of bytes delivered.
+    pub bytes: usize,
+
+    /// Time from the first frame loaded until the TX FIFO emptied.
+    pub elapsed: Duration,
+}
+
+impl StreamReport {
+    /// Returns the achieved throughput of the delivered data in bits per second.
+    pub fn throughput(&self) -> u32 {
+        match self.elapsed.as_micros() {
+            0 => 0,
+            us => ((self.bytes as u64 * 8 * 1_000_000) / us) as u32,
+        }
+    }
+}
+
+
+
+impl<SPI: SpiBus + SpiBusWrite, CS: OutputPin, CE: OutputPin, IRQ: Wait, FE: FrontEnd> Receiver<SPI, CS, CE, IRQ, FE> {
+    /// Longest time CE is held high in the original part, which must not stay in TX mode over
+    /// 4 ms. Leaves room for the frame in flight to end before the limit.
+    /// Only streams without acknowledge need it, as waiting for each ACK leaves TX mode.
+    pub const TXMAX: Duration = Duration::from_micros(3600);
+
+    /// Air time of the longest frame at 1 Mbps, waited with CE low before it rises again.
+    pub const TXFRAME: Duration = Duration::from_micros(400);
+
+    /// Transmits the data to the given address in frames of 32 bytes (the last one may be shorter,
+    /// so the receiving pipe should have a dynamic width), with the given acknowledge policy.
+    /// After MAX_RT the frame is retransmitted up to `retries` more rounds, then it is dropped
+    /// and the stream goes on with the next frames. Frames without acknowledge are never lost
+    /// as far as the device knows.
+    /// The device returns to its previous state afterwards.
+    pub async fn stream(&mut self, address: [u8; 5], data: &[u8], ack: AckPolicy, retries: u8) -> Result<StreamReport, SPI::Error> {
+        // Number of frames.
+        let frames = data.len().div_ceil(32);
+
+        if frames == 0 {
+            return Ok( StreamReport { frames, lost: 0, bytes: 0, elapsed: Duration::from_ticks(0), } );
+        }
+
+        let previous = self.txmode(address).await?;
+
+        let outcome = self.transmit(data, frames, ack, retries).await;
+
+        // Set CE low and drop any frames left by an error.
+        self.base.disable();
+        self.base.command( Command::TXFlush ).await?;
+
+        self.rxmode(previous).await?;
+
+        let (lost, dropped, elapsed) = outcome?;
+
+        Ok( StreamReport { frames, lost, bytes: data.len() - dropped, elapsed, } )
+    }
+
+    /// Transmits the frames of the data with CE held high.
+    /// Returns the number of frames and bytes dropped and the time until the TX FIFO emptied.
+    async fn transmit(&mut self, data: &[u8], frames: usize, ack: AckPolicy, retries: u8) -> Result<(usize, usize, Duration), SPI::Error> {
+        let start = Instant::now();
+
+        // Index of the next frame to load.
+        let mut next = self.fill(data, 0, ack).await?;
+
+        // Index of the frame at the head of the TX FIFO.
+        let mut head = 0;
+
+        // Extra rounds of retransmits of the frame at the head of the FIFO.
+        let mut attempts = 0;
+
+        // Frames and bytes dropped.
+        let (mut lost, mut dropped) = (0, 0);
+
+        // Only the original part has a limit of time in TX mode, reached without acknowledges.
+        let window = match (self.chip, ack) {
+            (ChipVariant::Nrf24l01, AckPolicy::None) => Some( Self::TXMAX ),
+            _ => None,
+        };
+
+        self.base.enable();
+        let mut since = Instant::now();
+
+        loop {
+            // Wait for the next event, or until the window of CE high closes.
+            let events = match window {
+                Some(window) => {
+                    let remaining = window.checked_sub(since.elapsed()).unwrap_or(Duration::from_ticks(0));
+
+                    // Only the wait on the pin is raced with the window, the events are cleared after it.
+                    match embassy_time::with_timeout(remaining, self.base.pending()).await {
+                        Ok(()) => Some( self.base.events().await? ),
+                        _ => None,
+                    }
+                },
+
+                _ => Some( self.base.wait().await? ),
+            };
+
+            let events = match events {
+                Some(events) => events,
+
+                _ => {
+                    // Leave TX after the frame in flight and enter it again.
+                    self.base.disable();
+                    Timer::after(Self::TXFRAME).await;
+                    self.base.enable();
+
+                    since = Instant::now();
+
+                    continue;
+                },
+            };
+
+            if events.txsent() {
+                attempts = 0;
+                head += 1;
+            }
+
+            // Frames sent back to back may share one TX_DS, so check the count against the FIFO
+            // where its flags leave no doubt.
+            let (_, fifo) = self.base.readreg(Register::FifoStatus).await?;
+
+            head = match fifo {
+                f if (f & (1 << 4)) != 0 => next,
+                f if (f & (1 << 5)) != 0 => next - 3,
+                _ => head.clamp(next.saturating_sub(2), next.saturating_sub(1)),
+            };
+
+            // Done when all the frames were loaded and sent.
+            if head == frames {
+                break;
+            }
+
+            // MAX_RT was cleared with CE still high, so the device leaves the frame at the head of
+            // the FIFO and starts another round of retransmits of it on its own. REUSE_TX_PL is not
+            // needed, and would keep resending the frame after it is acknowledged.
+            if events.maxretries() {
+                attempts += 1;
+
+                if attempts > retries {
+                    self.base.disable();
+
+                    self.base.command( Command::TXFlush ).await?;
+
+                    lost += 1;
+                    dropped += Self::frame(data, head).len();
+                    attempts = 0;
+
+                    // Reload the frames after the dropped one.
+                    head += 1;
+                    next = self.fill(data, head, ack).await?;
+
+                    if head == frames {
+                        break;
+                    }
+
+                    self.base.enable();
+                    since = Instant::now();
+                }
+            }
+
+            next = self.fill(data, next, ack).await?;
+        }
+
+        Ok( (lost, dropped, start.elapsed()) )
+    }
+
+    /// Returns the frame of the data at the given index.
+    fn frame(data: &[u8], n: usize) -> &[u8] {
+        &data[n * 32..data.len().min((n + 1) * 32)]
+    }
+
+    /// Loads frames from the given index until the TX FIFO is full or the data ends.
+    /// Returns the index of the next frame to load.
+    async fn fill(&mut self, data: &[u8], mut next: usize, ack: AckPolicy) -> Result<usize, SPI::Error> {
+        while (next * 32) < data.len() {
+            if self.base.command( Command::Nop ).await?.txfull() {
+                break;
+            }
+
+            self.base.txpayload( Self::frame(data, next), ack ).await?;
+
+            next += 1;
+        }
+
+        Ok( next )
+    }
+}
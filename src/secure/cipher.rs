@@ -0,0 +1,17 @@
+//! Authenticated encryption backend of the secure layer.
+//! Implement this trait over a software library or a hardware AES peripheral.
+
+
+
+pub trait Cipher {
+    /// Size of the authentication tag in bytes.
+    const TAG: usize;
+
+    /// Encrypts the data in place and writes the authentication tag.
+    /// The tag buffer is exactly `TAG` bytes long.
+    fn seal(&mut self, nonce: &[u8; 13], data: &mut [u8], tag: &mut [u8]);
+
+    /// Authenticates the data against the tag and decrypts it in place.
+    /// Returns `false` if the authentication failed.
+    fn open(&mut self, nonce: &[u8; 13], data: &mut [u8], tag: &[u8]) -> bool;
+}
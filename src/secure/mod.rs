@@ -0,0 +1,197 @@
+//! Link-layer encryption and authentication of payloads.
+//! Every frame carries a 4 byte frame counter, the encrypted message and the authentication tag.
+//! The nonce is built from the address of the sender and the frame counter, and replayed frames
+//! are rejected with a sliding window.
+
+
+
+mod cipher;
+#[cfg(feature = "pairing")]
+pub mod pairing;
+#[cfg(feature = "rustcrypto")]
+mod rustcrypto;
+mod window;
+
+
+
+pub use cipher::Cipher;
+#[cfg(feature = "rustcrypto")]
+pub use rustcrypto::{ SoftwareCcm, SoftwareChaCha };
+pub use window::ReplayWindow;
+
+
+
+use embedded_hal::{
+    digital::v2::{
+        OutputPin,
+    },
+};
+
+use embedded_hal_async::{
+    digital::{
+        Wait,
+    },
+    spi::{
+        SpiBus, SpiBusWrite,
+    },
+};
+
+use crate::{
+    common::{
+        AckPolicy, Error,
+    },
+    frontend::FrontEnd,
+    rx::{
+        Payload, Receiver,
+    },
+};
+
+
+
+pub struct Link<C: Cipher> {
+    /// Cipher keyed with the link key.
+    cipher: C,
+
+    /// Address of this node.
+    local: [u8; 5],
+
+    /// Address of the peer.
+    peer: [u8; 5],
+
+    /// Counter of the next frame sent.
+    counter: u32,
+
+    /// Counters of the frames received.
+    window: ReplayWindow,
+}
+
+impl<C: Cipher> Link<C> {
+    /// Size of the frame counter in bytes.
+    pub const COUNTER: usize = 4;
+
+    /// Maximum size of the message carried by a frame.
+    pub const MAXMESSAGE: usize = 32 - Self::COUNTER - C::TAG;
+
+    /// Creates a new link between this node and a peer.
+    pub const fn new(cipher: C, local: [u8; 5], peer: [u8; 5]) -> Self {
+        Self { cipher, local, peer, counter: 0, window: ReplayWindow::new(), }
+    }
+
+    /// Restores a link with the frame counter saved before a reset.
+    /// Reusing a frame counter with the same key breaks the encryption.
+    pub const fn restore(cipher: C, local: [u8; 5], peer: [u8; 5], counter: u32) -> Self {
+        Self { cipher, local, peer, counter, window: ReplayWindow::new(), }
+    }
+
+    /// Returns the counter of the next frame sent.
+    pub fn counter(&self) -> u32 {
+        self.counter
+    }
+
+    /// Encrypts a message into a frame.
+    /// Returns the length of the frame, or `None` if the message is too long or the counter is exhausted.
+    pub fn seal(&mut self, message: &[u8], frame: &mut [u8; 32]) -> Option<usize> {
+        if (message.len() > Self::MAXMESSAGE) || (self.counter == u32::MAX) {
+            return None;
+        }
+
+        let counter = self.counter;
+        self.counter += 1;
+
+        // Lengths of each part.
+        let end = Self::COUNTER + message.len();
+        let len = end + C::TAG;
+
+        // Build the frame.
+        frame[..Self::COUNTER].copy_from_slice(&counter.to_le_bytes());
+        frame[Self::COUNTER..end].copy_from_slice(message);
+
+        let (data, tag) = frame[Self::COUNTER..len].split_at_mut(message.len());
+
+        self.cipher.seal(&Self::nonce(&self.local, counter), data, tag);
+
+        Some( len )
+    }
+
+    /// Authenticates and decrypts a frame received from the peer.
+    /// Returns `None` if the frame is malformed, forged or replayed.
+    pub fn open(&mut self, frame: &[u8]) -> Option<Message> {
+        if (frame.len() < Self::COUNTER + C::TAG) || (frame.len() > 32) {
+            return None;
+        }
+
+        // Check the counter before spending time in the cipher.
+        let counter = u32::from_le_bytes([frame[0], frame[1], frame[2], frame[3]]);
+
+        if !self.window.check(counter) {
+            return None;
+        }
+
+        // Split the frame.
+        let len = frame.len() - Self::COUNTER - C::TAG;
+        let end = Self::COUNTER + len;
+
+        let mut message = Message { len, data: [0; 32], };
+        message.data[..len].copy_from_slice(&frame[Self::COUNTER..end]);
+
+        if !self.cipher.open(&Self::nonce(&self.peer, counter), &mut message.data[..len], &frame[end..]) {
+            return None;
+        }
+
+        self.window.update(counter);
+
+        Some( message )
+    }
+
+    /// Encrypts a message and sends it to the peer.
+    /// Returns `true` if the peer acknowledged the frame.
+    /// Fails with `Exhausted` once the frame counter runs out, after which the link must be
+    /// paired again to get a new key.
+    pub async fn send<SPI: SpiBus + SpiBusWrite, CS: OutputPin, CE: OutputPin, IRQ: Wait, FE: FrontEnd>(&mut self, radio: &mut Receiver<SPI, CS, CE, IRQ, FE>, message: &[u8]) -> Result<bool, Error<SPI::Error>> {
+        if self.counter == u32::MAX {
+            return Err( Error::Exhausted );
+        }
+
+        let mut frame = [0; 32];
+
+        let len = match self.seal(message, &mut frame) {
+            Some(len) => len,
+            _ => return Err( Error::Length ),
+        };
+
+        radio.send(self.peer, &frame[..len], AckPolicy::Required).await.map_err(Error::Spi)
+    }
+
+    /// Authenticates and decrypts a payload received from the peer.
+    pub fn recv(&mut self, payload: &Payload) -> Option<Message> {
+        self.open(payload.view())
+    }
+
+    /// Builds the nonce of a frame.
+    fn nonce(address: &[u8; 5], counter: u32) -> [u8; 13] {
+        let mut nonce = [0; 13];
+
+        nonce[..5].copy_from_slice(address);
+        nonce[5..9].copy_from_slice(&counter.to_le_bytes());
+
+        nonce
+    }
+}
+
+
+
+#[derive(Clone)]
+pub struct Message {
+    /// Length of the message.
+    len: usize,
+
+    /// Raw decrypted data.
+    data: [u8; 32],
+}
+
+impl Message {
+    /// Returns a view into the message.
+    pub fn view(&self) -> &[u8] {
+        &self.data[0..self.len]
+    }
+}
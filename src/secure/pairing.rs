@@ -0,0 +1,572 @@
+//! Pairing of new nodes and provisioning of link keys.
+//! Both nodes meet in a well-known pairing channel and address and exchange 32 byte shares in
+//! two fragments each.
+//! With a pre-shared PIN the shares are CPace messages over ristretto255, so an eavesdropper or a
+//! man in the middle learns nothing to test PIN guesses offline and gets one guess per run.
+//! With a short authentication string the shares are X25519 public keys, and the initiator commits
+//! to its key before seeing the key of the responder, so a man in the middle cannot search for
+//! keys that give both sides the same code.
+
+
+
+use embassy_time::{
+    Duration, Instant, Timer,
+};
+
+use embedded_hal::{
+    digital::v2::{
+        OutputPin,
+    },
+};
+
+use embedded_hal_async::{
+    digital::{
+        Wait,
+    },
+    spi::{
+        SpiBus, SpiBusWrite,
+    },
+};
+
+use hkdf::Hkdf;
+
+use hmac::{
+    Hmac, Mac,
+};
+
+use sha2::{
+    Sha256, Sha512,
+};
+
+use curve25519_dalek::{
+    ristretto::{
+        CompressedRistretto, RistrettoPoint,
+    },
+    scalar::Scalar,
+    traits::IsIdentity,
+};
+
+use x25519_dalek::{
+    x25519, X25519_BASEPOINT_BYTES,
+};
+
+use crate::{
+    common::{
+        AckPolicy, AddressWidth, Channel, CRCBytes, Error,
+    },
+    frontend::FrontEnd,
+    rx::{
+        Config, Pipe, PipeAddress, Receiver,
+    },
+};
+
+
+
+/// Persistent storage of link keys.
+pub trait KeyStore {
+    /// Error of the storage.
+    type Error;
+
+    /// Stores the link key of a peer.
+    fn store(&mut self, peer: &[u8; 5], key: &[u8; 32]) -> Result<(), Self::Error>;
+
+    /// Loads the link key of a peer.
+    fn load(&mut self, peer: &[u8; 5]) -> Result<Option<[u8; 32]>, Self::Error>;
+}
+
+
+
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub enum Role {
+    /// The new node, which starts the exchange.
+    Initiator,
+
+    /// The node in pairing mode, usually the gateway.
+    Responder,
+}
+
+
+
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub enum Method {
+    /// Both nodes know the same PIN.
+    Pin(u32),
+
+    /// The user compares a 6 digit code shown by both nodes.
+    Sas,
+}
+
+
+
+pub struct Pairing {
+    /// X25519 or CPace secret of this node.
+    secret: [u8; 32],
+
+    /// Address of this node.
+    local: [u8; 5],
+
+    /// Role of this node in the exchange.
+    role: Role,
+
+    /// Confirmation method.
+    method: Method,
+}
+
+impl Pairing {
+    /// Channel used for pairing.
+    pub const CHANNEL: Channel = Channel::saturating(2);
+
+    /// Address used for pairing.
+    pub const ADDRESS: [u8; 5] = [0xA5, 0x50, 0x41, 0x49, 0x52];
+
+    /// Share fragment.
+    const KEY: u8 = 0x01;
+
+    /// Key confirmation.
+    const CONFIRM: u8 = 0x02;
+
+    /// Commitment fragment.
+    const COMMIT: u8 = 0x03;
+
+    /// Time between retransmissions.
+    const RETRY: Duration = Duration::from_millis(10);
+
+    /// Creates a pairing exchange.
+    /// The secret must come from a cryptographically secure random generator and must not be reused.
+    pub const fn new(secret: [u8; 32], local: [u8; 5], role: Role, method: Method) -> Self {
+        Self { secret, local, role, method }
+    }
+
+    /// Builds the receiver configuration used during pairing.
+    pub const fn config(current: &Config) -> Config {
+        Config::configured(
+            Self::CHANNEL,
+            current.datarate,
+            current.gain,
+            AddressWidth::FiveBytes,
+            Some( CRCBytes::TwoBytes ),
+            [Some( Pipe::dynamic(PipeAddress::Full(Self::ADDRESS), true) ), None, None, None, None, None],
+        )
+    }
+
+    /// Runs the exchange with the peer.
+    /// Returns `None` if the peer did not answer in time or the confirmation failed.
+    /// The receiver returns to its previous configuration afterwards.
+    pub async fn run<SPI: SpiBus + SpiBusWrite, CS: OutputPin, CE: OutputPin, IRQ: Wait, FE: FrontEnd>(&self, radio: &mut Receiver<SPI, CS, CE, IRQ, FE>, timeout: Duration) -> Result<Option<Paired>, Error<SPI::Error>> {
+        // Configuration to restore.
+        let previous = *radio.config();
+
+        radio.configure(Self::config(&previous)).await?;
+        radio.listen().await.map_err(Error::Spi)?;
+
+        let result = self.exchange(radio, Instant::now() + timeout).await;
+
+        radio.configure(previous).await?;
+
+        result
+    }
+
+    /// Returns the value sent to the peer: the X25519 public key with `Sas`, or the CPace share
+    /// with `Pin`.
+    pub fn share(&self) -> [u8; 32] {
+        match self.method {
+            Method::Pin(pin) => (self.scalar() * Self::generator(pin)).compress().to_bytes(),
+            Method::Sas => x25519(self.secret, X25519_BASEPOINT_BYTES),
+        }
+    }
+
+    /// Returns the commitment to the share, sent by the initiator before the shares with `Sas`.
+    pub fn commitment(&self) -> [u8; 32] {
+        Self::commit(&self.share(), &self.local)
+    }
+
+    /// Checks that the share and address of the peer match the commitment it sent.
+    pub fn opens(commitment: &[u8; 32], share: &[u8; 32], address: &[u8; 5]) -> bool {
+        Self::commit(share, address) == *commitment
+    }
+
+    /// Derives the keys from the share of the peer.
+    /// Returns `None` if the share is invalid or yields a degenerate secret.
+    pub fn derive(&self, peer: [u8; 5], theirs: [u8; 32]) -> Option<Keys> {
+        let ours = self.share();
+
+        let shared = match self.method {
+            Method::Pin(_) => {
+                let point = CompressedRistretto(theirs).decompress()?;
+                let shared = self.scalar() * point;
+
+                if shared.is_identity() {
+                    return None;
+                }
+
+                shared.compress().to_bytes()
+            },
+
+            Method::Sas => {
+                let shared = x25519(self.secret, theirs);
+
+                if shared == [0; 32] {
+                    return None;
+                }
+
+                shared
+            },
+        };
+
+        let transcript = match self.role {
+            Role::Initiator => Transcript { keys: [ours, theirs], addresses: [self.local, peer] },
+            Role::Responder => Transcript { keys: [theirs, ours], addresses: [peer, self.local] },
+        };
+
+        let salt: &[u8] = match self.method {
+            Method::Pin(_) => b"nrf24 cpace",
+            Method::Sas => b"nrf24 sas",
+        };
+
+        let hkdf = Hkdf::<Sha256>::new(Some(salt), &shared);
+
+        let mut link = [0; 32];
+        let mut confirm = [0; 32];
+        let mut sas = [0; 4];
+
+        // Safety: The outputs are far below the HKDF limit of 8160 bytes.
+        hkdf.expand_multi_info(&transcript.info(b"nrf24 link"), &mut link).unwrap();
+        hkdf.expand_multi_info(&transcript.info(b"nrf24 confirm"), &mut confirm).unwrap();
+        hkdf.expand_multi_info(&transcript.info(b"nrf24 sas"), &mut sas).unwrap();
+
+        let sas = match self.method {
+            Method::Sas => Some( u32::from_le_bytes(sas) % 1_000_000 ),
+            _ => None,
+        };
+
+        Some( Keys { peer, link, confirm, sas, transcript } )
+    }
+
+    /// Exchanges and confirms the keys.
+    async fn exchange<SPI: SpiBus + SpiBusWrite, CS: OutputPin, CE: OutputPin, IRQ: Wait, FE: FrontEnd>(&self, radio: &mut Receiver<SPI, CS, CE, IRQ, FE>, deadline: Instant) -> Result<Option<Paired>, Error<SPI::Error>> {
+        let share = self.share();
+
+        // Exchange the shares. With `Sas` the initiator commits to its share first, so neither
+        // side can pick its key after seeing the other one.
+        let peer = match (self.role, self.method) {
+            (Role::Initiator, Method::Pin(_)) => {
+                if !Self::sendkey(radio, Self::KEY, &self.local, &share, deadline).await? {
+                    return Ok( None );
+                }
+
+                Self::recvkey(radio, Self::KEY, deadline).await?
+            },
+
+            (Role::Responder, Method::Pin(_)) => {
+                let peer = Self::recvkey(radio, Self::KEY, deadline).await?;
+
+                if peer.is_some() && !Self::sendkey(radio, Self::KEY, &self.local, &share, deadline).await? {
+                    return Ok( None );
+                }
+
+                peer
+            },
+
+            (Role::Initiator, Method::Sas) => {
+                if !Self::sendkey(radio, Self::COMMIT, &self.local, &self.commitment(), deadline).await? {
+                    return Ok( None );
+                }
+
+                let peer = Self::recvkey(radio, Self::KEY, deadline).await?;
+
+                if peer.is_some() && !Self::sendkey(radio, Self::KEY, &self.local, &share, deadline).await? {
+                    return Ok( None );
+                }
+
+                peer
+            },
+
+            (Role::Responder, Method::Sas) => {
+                let (address, commitment) = match Self::recvkey(radio, Self::COMMIT, deadline).await? {
+                    Some(commitment) => commitment,
+                    _ => return Ok( None ),
+                };
+
+                if !Self::sendkey(radio, Self::KEY, &self.local, &share, deadline).await? {
+                    return Ok( None );
+                }
+
+                match Self::recvkey(radio, Self::KEY, deadline).await? {
+                    Some((sender, key)) if (sender == address) && Self::opens(&commitment, &key, &sender) => Some( (sender, key) ),
+                    _ => return Ok( None ),
+                }
+            },
+        };
+
+        let keys = match peer.and_then(|(address, key)| self.derive(address, key)) {
+            Some(keys) => keys,
+            _ => return Ok( None ),
+        };
+
+        // Confirm the keys.
+        let theirs = match self.role {
+            Role::Initiator => Role::Responder,
+            Role::Responder => Role::Initiator,
+        };
+
+        let mut message = [0; 17];
+        message[0] = Self::CONFIRM;
+        message[1..].copy_from_slice(&keys.confirmation(self.role));
+
+        let received = match self.role {
+            Role::Initiator => {
+                if !Self::sendretry(radio, &message, deadline).await? {
+                    return Ok( None );
+                }
+
+                Self::recvconfirm(radio, deadline).await?
+            },
+
+            Role::Responder => {
+                let received = Self::recvconfirm(radio, deadline).await?;
+
+                // Do not reveal our confirmation to a peer with the wrong key.
+                if let Some(received) = received {
+                    if !keys.verify(theirs, &received) {
+                        return Ok( None );
+                    }
+
+                    if !Self::sendretry(radio, &message, deadline).await? {
+                        return Ok( None );
+                    }
+                }
+
+                received
+            },
+        };
+
+        match received {
+            Some(received) if keys.verify(theirs, &received) => (),
+            _ => return Ok( None ),
+        }
+
+        Ok( Some( Paired { peer: keys.peer, key: keys.link, sas: keys.sas } ) )
+    }
+
+    /// Returns the CPace scalar of this node.
+    fn scalar(&self) -> Scalar {
+        Scalar::hash_from_bytes::<Sha512>(&self.secret)
+    }
+
+    /// Returns the CPace generator of the PIN.
+    /// Its discrete logarithm is unknown, so the shares reveal nothing about the PIN.
+    fn generator(pin: u32) -> RistrettoPoint {
+        let mut input = [0; 15];
+        input[..11].copy_from_slice(b"nrf24 cpace");
+        input[11..].copy_from_slice(&pin.to_le_bytes());
+
+        RistrettoPoint::hash_from_bytes::<Sha512>(&input)
+    }
+
+    /// Builds the commitment to a share.
+    fn commit(share: &[u8; 32], address: &[u8; 5]) -> [u8; 32] {
+        use sha2::Digest;
+
+        let mut hash = Sha256::new();
+
+        hash.update(b"nrf24 commit");
+        hash.update(share);
+        hash.update(address);
+
+        hash.finalize().into()
+    }
+
+    /// Sends a share or commitment in two fragments.
+    async fn sendkey<SPI: SpiBus + SpiBusWrite, CS: OutputPin, CE: OutputPin, IRQ: Wait, FE: FrontEnd>(radio: &mut Receiver<SPI, CS, CE, IRQ, FE>, kind: u8, local: &[u8; 5], value: &[u8; 32], deadline: Instant) -> Result<bool, Error<SPI::Error>> {
+        for (i, fragment) in value.chunks(16).enumerate() {
+            let mut message = [0; 23];
+            message[0] = kind;
+            message[1] = i as u8;
+            message[2..7].copy_from_slice(local);
+            message[7..].copy_from_slice(fragment);
+
+            if !Self::sendretry(radio, &message, deadline).await? {
+                return Ok( false );
+            }
+        }
+
+        Ok( true )
+    }
+
+    /// Receives the two fragments of a share or commitment of the peer.
+    async fn recvkey<SPI: SpiBus + SpiBusWrite, CS: OutputPin, CE: OutputPin, IRQ: Wait, FE: FrontEnd>(radio: &mut Receiver<SPI, CS, CE, IRQ, FE>, kind: u8, deadline: Instant) -> Result<Option<([u8; 5], [u8; 32])>, Error<SPI::Error>> {
+        // Address of the peer.
+        let mut address = None;
+
+        // Value sent by the peer.
+        let mut key = [0; 32];
+
+        // Fragments received.
+        let mut received = [false; 2];
+
+        while Instant::now() < deadline {
+            let payload = match radio.recv_deadline(deadline).await {
+                Ok(payload) => payload,
+                Err(Error::Timeout) => break,
+                Err(e) => return Err( e ),
+            };
+
+            let data = payload.view();
+
+            if (data.len() != 23) || (data[0] != kind) || (data[1] > 1) {
+                continue;
+            }
+
+            // All fragments must come from the same peer.
+            let sender = <[u8; 5]>::try_from(&data[2..7]).unwrap();
+
+            match address {
+                Some(a) if a != sender => continue,
+                _ => address = Some( sender ),
+            }
+
+            let i = data[1] as usize;
+            key[i * 16..(i + 1) * 16].copy_from_slice(&data[7..]);
+            received[i] = true;
+
+            if received[0] && received[1] {
+                return Ok( address.map(|a| (a, key)) );
+            }
+        }
+
+        Ok( None )
+    }
+
+    /// Receives the confirmation of the peer.
+    async fn recvconfirm<SPI: SpiBus + SpiBusWrite, CS: OutputPin, CE: OutputPin, IRQ: Wait, FE: FrontEnd>(radio: &mut Receiver<SPI, CS, CE, IRQ, FE>, deadline: Instant) -> Result<Option<[u8; 16]>, Error<SPI::Error>> {
+        while Instant::now() < deadline {
+            let payload = match radio.recv_deadline(deadline).await {
+                Ok(payload) => payload,
+                Err(Error::Timeout) => break,
+                Err(e) => return Err( e ),
+            };
+
+            let data = payload.view();
+
+            if (data.len() == 17) && (data[0] == Self::CONFIRM) {
+                return Ok( Some( <[u8; 16]>::try_from(&data[1..]).unwrap() ) );
+            }
+        }
+
+        Ok( None )
+    }
+
+    /// Sends a message until the peer acknowledges it or the deadline expires.
+    async fn sendretry<SPI: SpiBus + SpiBusWrite, CS: OutputPin, CE: OutputPin, IRQ: Wait, FE: FrontEnd>(radio: &mut Receiver<SPI, CS, CE, IRQ, FE>, message: &[u8], deadline: Instant) -> Result<bool, Error<SPI::Error>> {
+        while Instant::now() < deadline {
+            if radio.send(Self::ADDRESS, message, AckPolicy::Required).await.map_err(Error::Spi)? {
+                return Ok( true );
+            }
+
+            Timer::after(Self::RETRY).await;
+        }
+
+        Ok( false )
+    }
+}
+
+
+
+/// Shares and addresses of both nodes, in initiator and responder order.
+struct Transcript {
+    /// Shares.
+    keys: [[u8; 32]; 2],
+
+    /// Addresses.
+    addresses: [[u8; 5]; 2],
+}
+
+impl Transcript {
+    /// Builds the HKDF info of the given label.
+    fn info<'a>(&'a self, label: &'a [u8]) -> [&'a [u8]; 5] {
+        [label, &self.keys[0], &self.keys[1], &self.addresses[0], &self.addresses[1]]
+    }
+
+    /// Starts the confirmation MAC of the given role.
+    fn confirmation(&self, key: &[u8; 32], role: Role) -> Hmac<Sha256> {
+        // Safety: HMAC accepts keys of any length.
+        let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(key).unwrap();
+
+        mac.update(&[role as u8]);
+        mac.update(&self.keys[0]);
+        mac.update(&self.keys[1]);
+
+        mac
+    }
+}
+
+
+
+pub struct Keys {
+    /// Address of the peer.
+    peer: [u8; 5],
+
+    /// Derived link key.
+    link: [u8; 32],
+
+    /// Key of the confirmation MACs.
+    confirm: [u8; 32],
+
+    /// Short authentication string, if the method requires it.
+    sas: Option<u32>,
+
+    /// Shares and addresses of both nodes.
+    transcript: Transcript,
+}
+
+impl Keys {
+    /// Returns the derived link key.
+    pub fn link(&self) -> &[u8; 32] {
+        &self.link
+    }
+
+    /// Returns the short authentication string, if the method requires it.
+    pub fn sas(&self) -> Option<u32> {
+        self.sas
+    }
+
+    /// Returns the confirmation sent by the given role.
+    pub fn confirmation(&self, role: Role) -> [u8; 16] {
+        let tag = self.transcript.confirmation(&self.confirm, role).finalize().into_bytes();
+
+        // Safety: The tag is 32 bytes long.
+        <[u8; 16]>::try_from(&tag[..16]).unwrap()
+    }
+
+    /// Checks the confirmation sent by the given role, in constant time.
+    pub fn verify(&self, role: Role, tag: &[u8; 16]) -> bool {
+        self.transcript.confirmation(&self.confirm, role).verify_truncated_left(tag).is_ok()
+    }
+}
+
+
+
+pub struct Paired {
+    /// Address of the peer.
+    pub peer: [u8; 5],
+
+    /// Derived link key.
+    key: [u8; 32],
+
+    /// Short authentication string to compare, if the method requires it.
+    pub sas: Option<u32>,
+}
+
+impl Paired {
+    /// Returns the derived link key.
+    /// AES-128 ciphers use the first 16 bytes.
+    pub fn key(&self) -> &[u8; 32] {
+        &self.key
+    }
+
+    /// Stores the link key.
+    /// With the `Sas` method, call this only after the user confirmed that both codes match.
+    pub fn store<S: KeyStore>(&self, store: &mut S) -> Result<(), S::Error> {
+        store.store(&self.peer, &self.key)
+    }
+}
@@ -0,0 +1,74 @@
+//! Software ciphers from the RustCrypto project.
+
+
+
+use aes::Aes128;
+
+use ccm::{
+    aead::{
+        AeadInPlace, KeyInit,
+    },
+    consts::{
+        U8, U13,
+    },
+    Ccm,
+};
+
+use chacha20poly1305::ChaCha20Poly1305;
+
+use super::Cipher;
+
+
+
+/// AES-128-CCM with an 8 byte tag.
+pub struct SoftwareCcm(Ccm<Aes128, U8, U13>);
+
+impl SoftwareCcm {
+    /// Creates the cipher with the given link key.
+    pub fn new(key: &[u8; 16]) -> Self {
+        Self( Ccm::new(key.into()) )
+    }
+}
+
+impl Cipher for SoftwareCcm {
+    const TAG: usize = 8;
+
+    fn seal(&mut self, nonce: &[u8; 13], data: &mut [u8], tag: &mut [u8]) {
+        // Safety: Encryption only fails for buffers longer than the CCM limits, far above 32 bytes.
+        let t = self.0.encrypt_in_place_detached(nonce.into(), &[], data).unwrap();
+
+        tag.copy_from_slice(&t);
+    }
+
+    fn open(&mut self, nonce: &[u8; 13], data: &mut [u8], tag: &[u8]) -> bool {
+        self.0.decrypt_in_place_detached(nonce.into(), &[], data, tag.into()).is_ok()
+    }
+}
+
+
+
+/// ChaCha20-Poly1305 with a 16 byte tag.
+/// Uses the first 12 bytes of the nonce.
+pub struct SoftwareChaCha(ChaCha20Poly1305);
+
+impl SoftwareChaCha {
+    /// Creates the cipher with the given link key.
+    pub fn new(key: &[u8; 32]) -> Self {
+        Self( ChaCha20Poly1305::new(key.into()) )
+    }
+}
+
+impl Cipher for SoftwareChaCha {
+    const TAG: usize = 16;
+
+    fn seal(&mut self, nonce: &[u8; 13], data: &mut [u8], tag: &mut [u8]) {
+        // Safety: Encryption only fails for buffers longer than 256 GiB.
+        let t = self.0.encrypt_in_place_detached(nonce[..12].into(), &[], data).unwrap();
+
+        tag.copy_from_slice(&t);
+    }
+
+    fn open(&mut self, nonce: &[u8; 13], data: &mut [u8], tag: &[u8]) -> bool {
+        self.0.decrypt_in_place_detached(nonce[..12].into(), &[], data, tag.into()).is_ok()
+    }
+}
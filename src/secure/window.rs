@@ -0,0 +1,67 @@
+//! Sliding replay window of received frame counters.
+
+
+
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub struct ReplayWindow {
+    /// Highest counter received.
+    highest: Option<u32>,
+
+    /// Bitmap of the received counters below the highest one.
+    /// Bit `n` is set if `highest - n` was received.
+    bitmap: u32,
+}
+
+impl ReplayWindow {
+    /// Number of counters tracked by the window.
+    pub const SIZE: u32 = 32;
+
+    /// Creates an empty window.
+    pub const fn new() -> Self {
+        ReplayWindow { highest: None, bitmap: 0 }
+    }
+
+    /// Returns `true` if the counter is new and inside the window.
+    pub const fn check(&self, counter: u32) -> bool {
+        match self.highest {
+            None => true,
+            Some(highest) if counter > highest => true,
+            Some(highest) => {
+                let offset = highest - counter;
+
+                (offset < Self::SIZE) && ((self.bitmap & (1 << offset)) == 0)
+            },
+        }
+    }
+
+    /// Marks the counter as received.
+    /// The counter must have been checked first.
+    pub fn update(&mut self, counter: u32) {
+        match self.highest {
+            Some(highest) if counter <= highest => self.bitmap |= 1 << (highest - counter),
+
+            Some(highest) => {
+                let shift = counter - highest;
+
+                self.bitmap = if shift < Self::SIZE {
+                    (self.bitmap << shift) | 1
+                } else {
+                    1
+                };
+
+                self.highest = Some( counter );
+            },
+
+            None => {
+                self.bitmap = 1;
+                self.highest = Some( counter );
+            },
+        }
+    }
+}
+
+impl Default for ReplayWindow {
+    fn default() -> Self {
+        Self::new()
+    }
+}
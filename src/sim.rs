@@ -0,0 +1,532 @@
+//! Software simulator of a NRF24L01+ device.
+//! Emulates the SPI command set, the register file and the FIFOs, so the driver and the tools
+//! can be exercised without hardware. Transmitted payloads are always acknowledged and received
+//! payloads are injected by the user.
+
+
+
+use core::{
+    cell::RefCell,
+    convert::Infallible,
+    future::poll_fn,
+    task::{
+        Poll, Waker,
+    },
+};
+
+use embedded_hal::{
+    digital::v2::{
+        OutputPin,
+    },
+};
+
+use embedded_hal_async::{
+    digital::{
+        Wait,
+    },
+    spi::{
+        ErrorType, SpiBus, SpiBusFlush, SpiBusRead, SpiBusWrite,
+    },
+};
+
+
+
+pub struct Simulator(RefCell<Device>);
+
+impl Simulator {
+    /// Creates a simulated device with the reset values of the registers.
+    pub const fn new() -> Self {
+        Self( RefCell::new( Device::new() ) )
+    }
+
+    /// Returns the SPI bus of the device.
+    pub fn spi(&self) -> SimSpi<'_> {
+        SimSpi(self)
+    }
+
+    /// Returns the CS pin of the device.
+    pub fn cs(&self) -> SimPin<'_> {
+        SimPin { sim: self, ce: false }
+    }
+
+    /// Returns the CE pin of the device.
+    pub fn ce(&self) -> SimPin<'_> {
+        SimPin { sim: self, ce: true }
+    }
+
+    /// Returns the IRQ pin of the device.
+    pub fn irq(&self) -> SimIrq<'_> {
+        SimIrq(self)
+    }
+
+    /// Delivers a payload to the given pipe, as if it arrived over the air.
+    /// Returns `false` if the device is not listening, the pipe is disabled or the RX FIFO is full.
+    pub fn inject(&self, pipe: u8, data: &[u8]) -> bool {
+        let mut device = self.0.borrow_mut();
+
+        let injected = device.inject(pipe, data);
+        device.wake();
+
+        injected
+    }
+
+    /// Sets the presence of a carrier in the given channel, detected through the RPD register.
+    pub fn carrier(&self, channel: u8, present: bool) {
+        let mut device = self.0.borrow_mut();
+
+        let bit = 1u128 << (channel & 0x7F);
+
+        if present {
+            device.carrier |= bit;
+        } else {
+            device.carrier &= !bit;
+        }
+    }
+
+    /// Returns the number of payloads transmitted by the device.
+    pub fn transmitted(&self) -> usize {
+        self.0.borrow().transmitted
+    }
+}
+
+impl Default for Simulator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+
+
+/// State of the simulated device.
+struct Device {
+    /// Single byte registers.
+    registers: [u8; 0x1E],
+
+    /// RX pipe 0, RX pipe 1 and TX addresses.
+    addresses: [[u8; 5]; 3],
+
+    /// RX FIFO of pipe, length and data.
+    rx: [(u8, u8, [u8; 32]); 3],
+
+    /// Number of payloads in the RX FIFO.
+    rxlen: usize,
+
+    /// TX FIFO of length and data.
+    tx: [(u8, [u8; 32]); 3],
+
+    /// Number of payloads in the TX FIFO.
+    txlen: usize,
+
+    /// Payload being written.
+    pending: (u8, [u8; 32]),
+
+    /// Command of the current transaction.
+    command: Option<u8>,
+
+    /// Data byte index in the current transaction.
+    index: usize,
+
+    /// CE signal.
+    ce: bool,
+
+    /// Channels with a carrier.
+    carrier: u128,
+
+    /// Number of payloads transmitted.
+    transmitted: usize,
+
+    /// Task waiting for the IRQ.
+    waker: Option<Waker>,
+}
+
+impl Device {
+    /// Creates the device with the reset values of the registers.
+    const fn new() -> Self {
+        let mut registers = [0; 0x1E];
+
+        registers[0x00] = 0x08;
+        registers[0x01] = 0x3F;
+        registers[0x02] = 0x03;
+        registers[0x03] = 0x03;
+        registers[0x04] = 0x03;
+        registers[0x05] = 0x02;
+        registers[0x06] = 0x0E;
+        registers[0x0C] = 0xC3;
+        registers[0x0D] = 0xC4;
+        registers[0x0E] = 0xC5;
+        registers[0x0F] = 0xC6;
+
+        Device {
+            registers,
+            addresses: [[0xE7; 5], [0xC2; 5], [0xE7; 5]],
+            rx: [(0, 0, [0; 32]); 3],
+            rxlen: 0,
+            tx: [(0, [0; 32]); 3],
+            txlen: 0,
+            pending: (0, [0; 32]),
+            command: None,
+            index: 0,
+            ce: false,
+            carrier: 0,
+            transmitted: 0,
+            waker: None,
+        }
+    }
+
+    /// Returns the STATUS register.
+    fn status(&self) -> u8 {
+        // Pipe of the payload at the head of the RX FIFO.
+        let pipe = match self.rxlen {
+            0 => 0b111,
+            _ => self.rx[0].0,
+        };
+
+        (self.registers[0x07] & 0x70) | (pipe << 1) | ((self.txlen == 3) as u8)
+    }
+
+    /// Returns the FIFO STATUS register.
+    fn fifo(&self) -> u8 {
+        ((self.rxlen == 0) as u8) | (((self.rxlen == 3) as u8) << 1) | (((self.txlen == 0) as u8) << 4) | (((self.txlen == 3) as u8) << 5)
+    }
+
+    /// Returns `true` if the IRQ signal is active.
+    fn irq(&self) -> bool {
+        (self.registers[0x07] & !self.registers[0x00] & 0x70) != 0
+    }
+
+    /// Returns `true` if the device is powered up and listening.
+    fn listening(&self) -> bool {
+        self.ce && ((self.registers[0x00] & 0b11) == 0b11)
+    }
+
+    /// Starts a new SPI transaction.
+    fn begin(&mut self) {
+        self.command = None;
+        self.index = 0;
+        self.pending.0 = 0;
+    }
+
+    /// Exchanges one byte of the current transaction.
+    fn byte(&mut self, input: u8) -> u8 {
+        let command = match self.command {
+            Some(command) => command,
+
+            None => {
+                self.command = Some( input );
+
+                return self.status();
+            },
+        };
+
+        let i = self.index;
+        self.index += 1;
+
+        match command {
+            // R_REGISTER.
+            0x00..=0x1F => self.read(command & 0x1F, i),
+
+            // W_REGISTER.
+            0x20..=0x3F => {
+                self.write(command & 0x1F, i, input);
+
+                0
+            },
+
+            // R_RX_PL_WID.
+            0x60 => self.rx[0].1,
+
+            // R_RX_PAYLOAD.
+            0x61 => self.rx[0].2.get(i).copied().unwrap_or(0),
+
+            // W_TX_PAYLOAD and W_TX_PAYLOAD_NOACK.
+            0xA0 | 0xB0 => {
+                if i < 32 {
+                    self.pending.1[i] = input;
+                    self.pending.0 = i as u8 + 1;
+                }
+
+                0
+            },
+
+            _ => 0,
+        }
+    }
+
+    /// Ends the current SPI transaction and executes its command.
+    fn end(&mut self) {
+        match self.command {
+            // R_RX_PAYLOAD.
+            Some(0x61) if self.rxlen > 0 => {
+                self.rx.rotate_left(1);
+                self.rxlen -= 1;
+            },
+
+            // W_TX_PAYLOAD and W_TX_PAYLOAD_NOACK.
+            Some(0xA0) | Some(0xB0) if self.txlen < 3 => {
+                self.tx[self.txlen] = self.pending;
+                self.txlen += 1;
+            },
+
+            // FLUSH_TX.
+            Some(0xE1) => self.txlen = 0,
+
+            // FLUSH_RX.
+            Some(0xE2) => self.rxlen = 0,
+
+            _ => (),
+        }
+
+        self.command = None;
+
+        self.update();
+    }
+
+    /// Reads a byte of a register.
+    fn read(&self, register: u8, i: usize) -> u8 {
+        match register {
+            0x07 => self.status(),
+
+            // RPD.
+            0x09 => (self.listening() && ((self.carrier >> (self.registers[0x05] & 0x7F)) & 1) != 0) as u8,
+
+            0x0A | 0x0B | 0x10 => self.addresses[Self::block(register)][i % 5],
+
+            0x17 => self.fifo(),
+
+            0x00..=0x1D => self.registers[register as usize],
+
+            _ => 0,
+        }
+    }
+
+    /// Writes a byte of a register.
+    fn write(&mut self, register: u8, i: usize, value: u8) {
+        match register {
+            // Clear the interrupts.
+            0x07 => self.registers[0x07] &= !(value & 0x70),
+
+            0x0A | 0x0B | 0x10 if i < 5 => self.addresses[Self::block(register)][i] = value,
+
+            // Bit 0 of RF_SETUP is obsolete in the NRF24L01+.
+            0x06 if i == 0 => self.registers[0x06] = value & 0xFE,
+
+            // Read only registers.
+            0x08 | 0x09 | 0x17 => (),
+
+            0x00..=0x1D if i == 0 => self.registers[register as usize] = value,
+
+            _ => (),
+        }
+    }
+
+    /// Returns the index of an address register.
+    fn block(register: u8) -> usize {
+        match register {
+            0x0A => 0,
+            0x0B => 1,
+            _ => 2,
+        }
+    }
+
+    /// Transmits the payloads in the TX FIFO if the device is in TX mode.
+    fn update(&mut self) {
+        let config = self.registers[0x00];
+
+        if self.ce && ((config & 0b11) == 0b10) {
+            while self.txlen > 0 {
+                self.tx.rotate_left(1);
+                self.txlen -= 1;
+
+                self.transmitted += 1;
+
+                // Every payload is acknowledged.
+                self.registers[0x07] |= 1 << 5;
+            }
+        }
+
+        self.wake();
+    }
+
+    /// Delivers a payload to the RX FIFO.
+    fn inject(&mut self, pipe: u8, data: &[u8]) -> bool {
+        let enabled = (pipe < 6) && ((self.registers[0x02] & (1 << pipe)) != 0);
+
+        if !self.listening() || !enabled || (self.rxlen == 3) {
+            return false;
+        }
+
+        // Length of the payload.
+        let len = data.len().min(32);
+
+        let mut payload = [0; 32];
+        payload[..len].copy_from_slice(&data[..len]);
+
+        self.rx[self.rxlen] = (pipe, len as u8, payload);
+        self.rxlen += 1;
+
+        self.registers[0x07] |= 1 << 6;
+
+        true
+    }
+
+    /// Wakes the task waiting for the IRQ.
+    fn wake(&mut self) {
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+
+
+pub struct SimSpi<'a>(&'a Simulator);
+
+impl<'a> ErrorType for SimSpi<'a> {
+    type Error = Infallible;
+}
+
+impl<'a> SpiBusFlush for SimSpi<'a> {
+    async fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+impl<'a> SpiBusRead for SimSpi<'a> {
+    async fn read(&mut self, words: &mut [u8]) -> Result<(), Self::Error> {
+        let mut device = self.0.0.borrow_mut();
+
+        for word in words.iter_mut() {
+            *word = device.byte(0);
+        }
+
+        Ok(())
+    }
+}
+
+impl<'a> SpiBusWrite for SimSpi<'a> {
+    async fn write(&mut self, words: &[u8]) -> Result<(), Self::Error> {
+        let mut device = self.0.0.borrow_mut();
+
+        for word in words.iter() {
+            device.byte(*word);
+        }
+
+        Ok(())
+    }
+}
+
+impl<'a> SpiBus for SimSpi<'a> {
+    async fn transfer<'b>(&'b mut self, read: &'b mut [u8], write: &'b [u8]) -> Result<(), Self::Error> {
+        let mut device = self.0.0.borrow_mut();
+
+        for i in 0..read.len().max(write.len()) {
+            let word = device.byte( write.get(i).copied().unwrap_or(0) );
+
+            if let Some(r) = read.get_mut(i) {
+                *r = word;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn transfer_in_place<'b>(&'b mut self, words: &'b mut [u8]) -> Result<(), Self::Error> {
+        let mut device = self.0.0.borrow_mut();
+
+        for word in words.iter_mut() {
+            *word = device.byte(*word);
+        }
+
+        Ok(())
+    }
+}
+
+
+
+pub struct SimPin<'a> {
+    /// The simulated device.
+    sim: &'a Simulator,
+
+    /// `true` for the CE pin, `false` for the CS pin.
+    ce: bool,
+}
+
+impl<'a> OutputPin for SimPin<'a> {
+    type Error = Infallible;
+
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        let mut device = self.sim.0.borrow_mut();
+
+        if self.ce {
+            device.ce = false;
+        } else {
+            device.begin();
+        }
+
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        let mut device = self.sim.0.borrow_mut();
+
+        if self.ce {
+            device.ce = true;
+            device.update();
+        } else {
+            device.end();
+        }
+
+        Ok(())
+    }
+}
+
+
+
+pub struct SimIrq<'a>(&'a Simulator);
+
+impl<'a> SimIrq<'a> {
+    /// Waits until the IRQ signal reaches the given state.
+    async fn level(&mut self, active: bool) -> Result<(), Infallible> {
+        poll_fn(|cx| {
+            let mut device = self.0.0.borrow_mut();
+
+            if device.irq() == active {
+                return Poll::Ready( Ok(()) );
+            }
+
+            device.waker = Some( cx.waker().clone() );
+
+            Poll::Pending
+        }).await
+    }
+}
+
+impl<'a> embedded_hal_1::digital::ErrorType for SimIrq<'a> {
+    type Error = Infallible;
+}
+
+impl<'a> Wait for SimIrq<'a> {
+    async fn wait_for_high(&mut self) -> Result<(), Self::Error> {
+        self.level(false).await
+    }
+
+    async fn wait_for_low(&mut self) -> Result<(), Self::Error> {
+        self.level(true).await
+    }
+
+    async fn wait_for_rising_edge(&mut self) -> Result<(), Self::Error> {
+        self.level(true).await?;
+        self.level(false).await
+    }
+
+    async fn wait_for_falling_edge(&mut self) -> Result<(), Self::Error> {
+        self.level(false).await?;
+        self.level(true).await
+    }
+
+    async fn wait_for_any_edge(&mut self) -> Result<(), Self::Error> {
+        let active = self.0.0.borrow().irq();
+
+        self.level(!active).await
+    }
+}
@@ -0,0 +1,198 @@
+//! RF test modes for certification and production testing.
+//!
+//! # Constant carrier
+//!
+//! Used for FCC/CE pre-compliance measurements of output power, frequency and spurious
+//! emissions with a spectrum analyzer.
+//!
+//! 1. Call `start_carrier` with the channel and gain under test. The device powers up in TX
+//!    mode, sets CONT_WAVE and PLL_LOCK in RF_SETUP and holds CE high.
+//! 2. Measure. The carrier stays on until `stop_carrier` is called.
+//! 3. Call `stop_carrier`. CE goes low and the configuration of the receiver is written again.
+//!
+//! The original NRF24L01 lacks CONT_WAVE and is rejected with `Unsupported`.
+//!
+//! # Packet error rate
+//!
+//! Used on the factory line with a golden unit as the peer. The peer listens on the test
+//! channels with auto-acknowledge on a pipe with the address given to the test.
+//!
+//! 1. Call `per_sweep` (or `per_test` for one channel) with the address of the peer.
+//! 2. The device sends numbered 32 byte packets on each channel and counts the acknowledged ones.
+//! 3. Each report gives the packet error rate and the mean number of retransmissions, a proxy
+//!    of the signal quality since the device has no RSSI.
+//!
+//! Accept the unit if every channel stays under the PER limit of the product.
+
+
+
+use embassy_time::{
+    Duration, Timer,
+};
+
+use embedded_hal::{
+    digital::v2::{
+        OutputPin,
+    },
+};
+
+use embedded_hal_async::{
+    digital::{
+        Wait,
+    },
+    spi::{
+        SpiBus, SpiBusWrite,
+    },
+};
+
+use crate::{
+    common::{
+        AckPolicy, Channel, Error, Gain, Register,
+    },
+    frontend::FrontEnd,
+    rx::Receiver,
+};
+
+
+
+/// CONT_WAVE bit of RF_SETUP.
+pub const CONT_WAVE: u8 = 1 << 7;
+
+/// PLL_LOCK bit of RF_SETUP.
+pub const PLL_LOCK: u8 = 1 << 4;
+
+/// Time from power down to standby.
+pub const STARTUP: Duration = Duration::from_micros(1500);
+
+
+
+/// Starts a constant carrier on the given channel and gain.
+pub async fn start_carrier<SPI: SpiBus + SpiBusWrite, CS: OutputPin, CE: OutputPin, IRQ: Wait, FE: FrontEnd>(rx: &mut Receiver<SPI, CS, CE, IRQ, FE>, channel: Channel, gain: Gain) -> Result<(), Error<SPI::Error>> {
+    if !rx.chip().contwave() {
+        return Err( Error::Unsupported );
+    }
+
+    rx.stop();
+
+    // Power up in TX mode.
+    let config = (rx.config().config() & !1) | (1 << 1);
+
+    rx.base.writereg(Register::Config, config).await.map_err(Error::Spi)?;
+
+    Timer::after(STARTUP).await;
+
+    // Set the carrier bits with the gain under test.
+    let mut setup = *rx.config();
+    setup.gain = gain;
+
+    let rfsetup = rx.chip().rfsetup(setup.rfsetup()) | CONT_WAVE | PLL_LOCK;
+
+    rx.base.writereg(Register::RFSetup, rfsetup).await.map_err(Error::Spi)?;
+    rx.base.writereg(Register::RFChannel, channel.raw()).await.map_err(Error::Spi)?;
+
+    // Hold CE high while the carrier is on.
+    rx.base.enable();
+
+    Ok(())
+}
+
+/// Stops the constant carrier and writes the configuration of the receiver again.
+pub async fn stop_carrier<SPI: SpiBus + SpiBusWrite, CS: OutputPin, CE: OutputPin, IRQ: Wait, FE: FrontEnd>(rx: &mut Receiver<SPI, CS, CE, IRQ, FE>) -> Result<(), Error<SPI::Error>> {
+    rx.base.disable();
+
+    let config = *rx.config();
+
+    rx.configure(config).await
+}
+
+
+
+/// Outcome of a packet error rate test on one channel.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct PerReport {
+    /// RF channel tested.
+    pub channel: Channel,
+
+    /// Packets sent.
+    pub sent: u16,
+
+    /// Packets acknowledged by the peer.
+    pub acked: u16,
+
+    /// Retransmissions of the acknowledged packets.
+    pub retries: u32,
+}
+
+impl PerReport {
+    /// Returns the packet error rate, from 0 to 1.
+    pub fn per(&self) -> f32 {
+        match self.sent {
+            0 => 0.0,
+            sent => (sent - self.acked) as f32 / sent as f32,
+        }
+    }
+
+    /// Returns the mean number of retransmissions of the acknowledged packets.
+    pub fn mean_retries(&self) -> f32 {
+        match self.acked {
+            0 => 0.0,
+            acked => self.retries as f32 / acked as f32,
+        }
+    }
+}
+
+/// Runs a packet error rate test on one channel.
+/// The channel of the receiver is restored afterwards.
+pub async fn per_test<SPI: SpiBus + SpiBusWrite, CS: OutputPin, CE: OutputPin, IRQ: Wait, FE: FrontEnd>(rx: &mut Receiver<SPI, CS, CE, IRQ, FE>, peer: [u8; 5], channel: Channel, packets: u16) -> Result<PerReport, Error<SPI::Error>> {
+    let previous = rx.config().channel;
+
+    rx.rfchannel(channel).await.map_err(Error::Spi)?;
+
+    let mut report = PerReport { channel, sent: 0, acked: 0, retries: 0, };
+
+    let outcome = per_packets(rx, peer, packets, &mut report).await;
+
+    // Restore the channel before any error is returned.
+    rx.rfchannel(previous).await.map_err(Error::Spi)?;
+
+    outcome.map_err(Error::Spi)?;
+
+    Ok( report )
+}
+
+/// Sends the numbered packets of a packet error rate test and counts them in the report.
+async fn per_packets<SPI: SpiBus + SpiBusWrite, CS: OutputPin, CE: OutputPin, IRQ: Wait, FE: FrontEnd>(rx: &mut Receiver<SPI, CS, CE, IRQ, FE>, peer: [u8; 5], packets: u16, report: &mut PerReport) -> Result<(), SPI::Error> {
+    // Numbered packet with a fixed pattern.
+    let mut packet = [0x55; 32];
+
+    for n in 0..packets {
+        packet[..2].copy_from_slice(&n.to_le_bytes());
+
+        let acked = rx.send(peer, &packet, AckPolicy::Required).await?;
+
+        report.sent += 1;
+
+        if acked {
+            // ARC_CNT counts the retransmissions of the last packet.
+            let (_, observe) = rx.base.readreg(Register::Observe).await?;
+
+            report.acked += 1;
+            report.retries += (observe & 0xF) as u32;
+        }
+    }
+
+    Ok(())
+}
+
+/// Runs a packet error rate test on each of the channels.
+/// Returns the number of reports written, limited by the length of `reports`.
+pub async fn per_sweep<SPI: SpiBus + SpiBusWrite, CS: OutputPin, CE: OutputPin, IRQ: Wait, FE: FrontEnd>(rx: &mut Receiver<SPI, CS, CE, IRQ, FE>, peer: [u8; 5], channels: &[Channel], packets: u16, reports: &mut [PerReport]) -> Result<usize, Error<SPI::Error>> {
+    let mut n = 0;
+
+    for (channel, report) in channels.iter().zip(reports.iter_mut()) {
+        *report = per_test(rx, peer, *channel, packets).await?;
+        n += 1;
+    }
+
+    Ok( n )
+}
@@ -0,0 +1,125 @@
+//! Validation of the receiver configuration builder.
+
+
+
+use nrf24::{
+    common::{
+        AddressWidth, Channel, ChipVariant, DataRate,
+    },
+    rx::{
+        Config, ConfigBuilder, ConfigError, Pipe, PipeAddress,
+    },
+};
+
+
+
+/// Full address of pipe 1.
+const P1: [u8; 5] = [0x01, 0xA1, 0xA2, 0xA3, 0xA4];
+
+/// Configuration evaluated at compile time.
+const CONFIG: Config = ConfigBuilder::new()
+    .channel(Channel::saturating(76))
+    .pipe(0, Pipe::dynamic(PipeAddress::Full([0xE7; 5]), true))
+    .pipe(1, Pipe::dynamic(PipeAddress::Full(P1), true))
+    .pipe(2, Pipe::sized(PipeAddress::Lsb(0x02), false, 8))
+    .chip(ChipVariant::Nrf24l01Plus)
+    .unwrap();
+
+/// Returns the errors found in the configuration.
+fn errors(builder: ConfigBuilder) -> Vec<ConfigError> {
+    builder.validate().iter().collect()
+}
+
+#[test]
+fn valid() {
+    assert!(CONFIG.channel == Channel::saturating(76));
+    assert_eq!(CONFIG.rxaddress(2), Some( [0x02, 0xA1, 0xA2, 0xA3, 0xA4] ));
+
+    assert!(ConfigBuilder::new().build().is_ok());
+}
+
+#[test]
+fn collision() {
+    let builder = ConfigBuilder::new()
+        .pipe(1, Pipe::dynamic(PipeAddress::Full(P1), true))
+        .pipe(3, Pipe::dynamic(PipeAddress::Lsb(0x01), true));
+
+    assert_eq!(errors(builder), [ConfigError::Collision(3)]);
+}
+
+#[test]
+fn address() {
+    let builder = ConfigBuilder::new()
+        .pipe(0, Pipe::dynamic(PipeAddress::Lsb(0x10), true));
+
+    assert_eq!(errors(builder), [ConfigError::Address]);
+}
+
+#[test]
+fn prefix() {
+    let builder = ConfigBuilder::new()
+        .pipe(1, Pipe::dynamic(PipeAddress::Full(P1), true))
+        .pipe(2, Pipe::dynamic(PipeAddress::Full([0x02, 0xB1, 0xB2, 0xB3, 0xB4]), true));
+
+    assert_eq!(errors(builder), [ConfigError::Prefix(2)]);
+
+    // Only the MSBs within the address width must match.
+    let builder = ConfigBuilder::new()
+        .addrwidth(AddressWidth::ThreeBytes)
+        .pipe(1, Pipe::dynamic(PipeAddress::Full(P1), true))
+        .pipe(2, Pipe::dynamic(PipeAddress::Full([0x02, 0xA1, 0xA2, 0xB3, 0xB4]), true));
+
+    assert!(errors(builder).is_empty());
+}
+
+#[test]
+fn width() {
+    let builder = ConfigBuilder::new()
+        .pipe(0, Pipe::sized(PipeAddress::Full([0xE7; 5]), true, 0))
+        .pipe(1, Pipe::sized(PipeAddress::Full(P1), true, 33));
+
+    assert_eq!(errors(builder), [ConfigError::Width(0), ConfigError::Width(1)]);
+}
+
+#[test]
+fn autoack() {
+    let builder = ConfigBuilder::new()
+        .crc(None)
+        .pipe(0, Pipe::dynamic(PipeAddress::Full([0xE7; 5]), false))
+        .pipe(1, Pipe::dynamic(PipeAddress::Full(P1), true));
+
+    assert_eq!(errors(builder), [ConfigError::AutoAck(1)]);
+}
+
+#[test]
+fn datarate() {
+    let builder = ConfigBuilder::new().datarate(DataRate::Low);
+
+    // Not checked without the variant of the device.
+    assert!(errors(builder).is_empty());
+
+    assert_eq!(errors(builder.chip(ChipVariant::Nrf24l01)), [ConfigError::DataRate]);
+    assert!(errors(builder.chip(ChipVariant::Nrf24l01Plus)).is_empty());
+}
+
+#[test]
+fn addrwidth() {
+    let builder = ConfigBuilder::new().addrwidth(AddressWidth::TwoBytes);
+
+    assert_eq!(errors(builder), [ConfigError::AddressWidth]);
+}
+
+#[test]
+fn build() {
+    let builder = ConfigBuilder::new()
+        .addrwidth(AddressWidth::TwoBytes)
+        .datarate(DataRate::Low)
+        .chip(ChipVariant::Nrf24l01);
+
+    let errors = builder.build().unwrap_err();
+
+    assert_eq!(errors.len(), 2);
+    assert_eq!(errors.get(0), Some( ConfigError::AddressWidth ));
+    assert_eq!(errors.get(1), Some( ConfigError::DataRate ));
+    assert_eq!(errors.get(2), None);
+}
@@ -0,0 +1,103 @@
+//! Regulatory plans and spacing of the RF channels.
+
+
+
+use nrf24::common::{
+    Channel, DataRate, Region,
+};
+
+
+
+/// Returns the channel with the given number.
+fn ch(n: u8) -> Channel {
+    Channel::new(n).unwrap()
+}
+
+#[test]
+fn frequency() {
+    assert_eq!(ch(0).mhz(), 2400);
+    assert_eq!(ch(76).mhz(), 2476);
+    assert_eq!(ch(Channel::MAX).mhz(), 2525);
+
+    assert_eq!(Channel::from_mhz(2402), Some( ch(2) ));
+    assert_eq!(Channel::from_mhz(2525), Some( ch(Channel::MAX) ));
+    assert_eq!(Channel::from_mhz(2399), None);
+    assert_eq!(Channel::from_mhz(2526), None);
+
+    assert_eq!(Channel::new(Channel::MAX + 1), None);
+    assert_eq!(Channel::saturating(200), ch(Channel::MAX));
+}
+
+#[test]
+fn lower_edge() {
+    for region in [Region::Fcc, Region::Etsi, Region::Japan] {
+        // Half of a 1 MHz channel falls under 2400 MHz.
+        assert!(!ch(0).allowed(region, DataRate::High));
+        assert!(ch(1).allowed(region, DataRate::High));
+
+        // A 2 MHz channel ends on 2400 MHz.
+        assert!(ch(1).allowed(region, DataRate::Max));
+        assert!(!ch(0).allowed(region, DataRate::Max));
+    }
+}
+
+#[test]
+fn fcc_upper_edge() {
+    // The band ends at 2481.5 MHz.
+    assert!(ch(81).allowed(Region::Fcc, DataRate::High));
+    assert!(!ch(82).allowed(Region::Fcc, DataRate::High));
+
+    assert!(ch(80).allowed(Region::Fcc, DataRate::Max));
+    assert!(!ch(81).allowed(Region::Fcc, DataRate::Max));
+}
+
+#[test]
+fn etsi_japan_upper_edge() {
+    // The band ends at 2483.5 MHz.
+    for region in [Region::Etsi, Region::Japan] {
+        assert!(ch(83).allowed(region, DataRate::High));
+        assert!(ch(83).allowed(region, DataRate::Low));
+        assert!(!ch(84).allowed(region, DataRate::High));
+
+        assert!(ch(82).allowed(region, DataRate::Max));
+        assert!(!ch(83).allowed(region, DataRate::Max));
+    }
+}
+
+#[test]
+fn spacing() {
+    // 2 Mbps channels need 2 MHz between them.
+    assert!(!ch(10).spaced(ch(11), DataRate::Max));
+    assert!(!ch(11).spaced(ch(10), DataRate::Max));
+    assert!(ch(10).spaced(ch(12), DataRate::Max));
+
+    assert!(ch(10).spaced(ch(11), DataRate::High));
+    assert!(!ch(10).spaced(ch(10), DataRate::High));
+}
+
+#[test]
+fn wifi() {
+    // WiFi channels 1, 6 and 11 are centered on 2412, 2437 and 2462 MHz.
+    for (wifi, center) in [(1, 12), (6, 37), (11, 62)] {
+        assert!(!ch(center).clear_of_wifi(wifi, DataRate::High));
+
+        // The 22 MHz mask and half of the 1 MHz channel overlap up to 12 MHz away.
+        assert!(!ch(center + 12).clear_of_wifi(wifi, DataRate::High));
+        assert!(ch(center + 13).clear_of_wifi(wifi, DataRate::High));
+        assert!(!ch(center - 12).clear_of_wifi(wifi, DataRate::High));
+    }
+
+    assert!(ch(24).clear_of_wifi(6, DataRate::Max));
+    assert!(ch(49).clear_of_wifi(11, DataRate::Max));
+
+    // Only the channels above WiFi channel 11 are clear of all three.
+    let mut out = [ch(0); 16];
+    let n = Channel::avoid_wifi(&[1, 6, 11], Region::Etsi, DataRate::High, &mut out);
+
+    assert_eq!(&out[..n], &(75..=83).map(ch).collect::<Vec<_>>()[..]);
+
+    // The FCC band at 2 Mbps ends at channel 80.
+    let n = Channel::avoid_wifi(&[1, 6, 11], Region::Fcc, DataRate::Max, &mut out);
+
+    assert_eq!(&out[..n], &(75..=80).map(ch).collect::<Vec<_>>()[..]);
+}
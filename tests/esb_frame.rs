@@ -0,0 +1,118 @@
+//! Round trips of the software ESB frame codec.
+
+
+
+use nrf24::{
+    common::{
+        AddressWidth, CRCBytes,
+    },
+    esb::{
+        crc, Format, Frame, Sniffed,
+    },
+};
+
+use proptest::prelude::*;
+
+
+
+/// Address width from its size in bytes.
+fn width(bytes: u8) -> AddressWidth {
+    match bytes {
+        2 => AddressWidth::TwoBytes,
+        3 => AddressWidth::ThreeBytes,
+        4 => AddressWidth::FourBytes,
+        _ => AddressWidth::FiveBytes,
+    }
+}
+
+/// CRC from its size in bytes.
+fn crcbytes(bytes: u8) -> Option<CRCBytes> {
+    match bytes {
+        1 => Some(CRCBytes::OneByte),
+        2 => Some(CRCBytes::TwoBytes),
+        _ => None,
+    }
+}
+
+/// Clears the address bytes beyond the width, which are not sent.
+fn address(width: AddressWidth, mut address: [u8; 5]) -> [u8; 5] {
+    address[width.bytes()..].fill(0);
+    address
+}
+
+
+
+#[test]
+fn crc16_check_value() {
+    assert_eq!(crc::crc16(b"123456789", 0, 72), 0x29B1);
+}
+
+proptest! {
+    #[test]
+    fn enhanced_round_trip(w in 2u8..=5, addr in any::<[u8; 5]>(), pid in 0u8..4, noack in any::<bool>(), c in 0u8..=2, payload in proptest::collection::vec(any::<u8>(), 0..=32)) {
+        let (width, crc) = (width(w), crcbytes(c));
+        let frame = Frame::enhanced(width, address(width, addr), pid, noack, crc, &payload).unwrap();
+
+        let mut buf = [0; Frame::MAXSIZE];
+        let bits = frame.encode(&mut buf).unwrap();
+
+        prop_assert_eq!(bits, frame.bits());
+
+        let decoded = Frame::decode(&buf, width, crc, Format::Enhanced).unwrap();
+
+        prop_assert!(decoded == frame);
+        prop_assert_eq!(decoded.view(), &payload[..]);
+    }
+
+    #[test]
+    fn legacy_round_trip(w in 2u8..=5, addr in any::<[u8; 5]>(), c in 0u8..=2, payload in proptest::collection::vec(any::<u8>(), 0..=32)) {
+        let (width, crc) = (width(w), crcbytes(c));
+        let frame = Frame::legacy(width, address(width, addr), crc, &payload).unwrap();
+
+        let mut buf = [0; Frame::MAXSIZE];
+        frame.encode(&mut buf).unwrap();
+
+        let decoded = Frame::decode(&buf, width, crc, Format::Legacy(payload.len() as u8)).unwrap();
+
+        prop_assert!(decoded == frame);
+    }
+
+    #[test]
+    fn payload_bit_flip_is_detected(w in 2u8..=5, addr in any::<[u8; 5]>(), payload in proptest::collection::vec(any::<u8>(), 1..=32), flip in any::<prop::sample::Index>()) {
+        let width = width(w);
+        let frame = Frame::enhanced(width, address(width, addr), 0, false, Some(CRCBytes::TwoBytes), &payload).unwrap();
+
+        let mut buf = [0; Frame::MAXSIZE];
+        frame.encode(&mut buf).unwrap();
+
+        // Flip one bit of the payload.
+        let bit = 8 + (width.bytes() * 8) + 9 + flip.index(payload.len() * 8);
+        buf[bit / 8] ^= 0x80 >> (bit % 8);
+
+        prop_assert!(Frame::decode(&buf, width, Some(CRCBytes::TwoBytes), Format::Enhanced).is_none());
+    }
+
+    #[test]
+    fn sniffer_finds_shifted_frames(addr in any::<[u8; 5]>(), payload in proptest::collection::vec(any::<u8>(), 0..=20), shift in 0usize..8, noise in any::<u8>()) {
+        let width = AddressWidth::FiveBytes;
+        let frame = Frame::enhanced(width, addr, 1, false, Some(CRCBytes::TwoBytes), &payload).unwrap();
+
+        let mut buf = [0; Frame::MAXSIZE];
+        frame.encode(&mut buf).unwrap();
+
+        // Capture without the preamble, delayed by a few noise bits.
+        let mut raw = [0u8; 32];
+
+        for i in 0..31 {
+            let word = ((buf[i] as u16) << 8) | (buf[i + 1] as u16);
+            raw[i] = (word >> shift) as u8;
+        }
+
+        raw[0] = (raw[0] & (0xFF >> shift)) | (noise & !(0xFF >> shift));
+
+        let sniffed = Sniffed::decode(&raw, width, CRCBytes::TwoBytes).unwrap();
+
+        prop_assert_eq!(sniffed.frame.address, addr);
+        prop_assert_eq!(sniffed.view(), &payload[..]);
+    }
+}
@@ -0,0 +1,116 @@
+//! Key derivation and confirmation of the pairing exchange.
+
+
+
+#![cfg(feature = "pairing")]
+
+
+
+use nrf24::secure::pairing::{
+    Method, Pairing, Role,
+};
+
+
+
+/// Address of the initiator.
+const A: [u8; 5] = [0xA1, 0xA2, 0xA3, 0xA4, 0xA5];
+
+/// Address of the responder.
+const B: [u8; 5] = [0xB1, 0xB2, 0xB3, 0xB4, 0xB5];
+
+/// Address of a man in the middle.
+const M: [u8; 5] = [0xC1, 0xC2, 0xC3, 0xC4, 0xC5];
+
+
+
+#[test]
+fn pin_agrees() {
+    let a = Pairing::new([1; 32], A, Role::Initiator, Method::Pin(123456));
+    let b = Pairing::new([2; 32], B, Role::Responder, Method::Pin(123456));
+
+    let ka = a.derive(B, b.share()).unwrap();
+    let kb = b.derive(A, a.share()).unwrap();
+
+    assert_eq!(ka.link(), kb.link());
+    assert_eq!(ka.sas(), None);
+
+    assert!(kb.verify(Role::Initiator, &ka.confirmation(Role::Initiator)));
+    assert!(ka.verify(Role::Responder, &kb.confirmation(Role::Responder)));
+
+    // A confirmation is not valid for the other role.
+    assert!(!ka.verify(Role::Responder, &ka.confirmation(Role::Initiator)));
+}
+
+#[test]
+fn pin_wrong() {
+    let a = Pairing::new([1; 32], A, Role::Initiator, Method::Pin(123456));
+    let b = Pairing::new([2; 32], B, Role::Responder, Method::Pin(123457));
+
+    let ka = a.derive(B, b.share()).unwrap();
+    let kb = b.derive(A, a.share()).unwrap();
+
+    assert_ne!(ka.link(), kb.link());
+    assert!(!kb.verify(Role::Initiator, &ka.confirmation(Role::Initiator)));
+}
+
+#[test]
+fn pin_share() {
+    // The share depends on the PIN, so it cannot be replayed under another one.
+    let a = Pairing::new([1; 32], A, Role::Initiator, Method::Pin(1));
+    let b = Pairing::new([1; 32], A, Role::Initiator, Method::Pin(2));
+
+    assert_ne!(a.share(), b.share());
+
+    // Invalid encodings and the identity are rejected.
+    assert!(a.derive(B, [0xFF; 32]).is_none());
+    assert!(a.derive(B, [0; 32]).is_none());
+}
+
+#[test]
+fn sas_agrees() {
+    let a = Pairing::new([1; 32], A, Role::Initiator, Method::Sas);
+    let b = Pairing::new([2; 32], B, Role::Responder, Method::Sas);
+
+    let ka = a.derive(B, b.share()).unwrap();
+    let kb = b.derive(A, a.share()).unwrap();
+
+    assert_eq!(ka.link(), kb.link());
+    assert_eq!(ka.sas(), kb.sas());
+    assert!(ka.sas().unwrap() < 1_000_000);
+
+    assert!(kb.verify(Role::Initiator, &ka.confirmation(Role::Initiator)));
+    assert!(ka.verify(Role::Responder, &kb.confirmation(Role::Responder)));
+
+    // Low order points give an all zero secret.
+    assert!(a.derive(B, [0; 32]).is_none());
+}
+
+#[test]
+fn sas_commitment() {
+    let a = Pairing::new([1; 32], A, Role::Initiator, Method::Sas);
+    let m = Pairing::new([3; 32], M, Role::Initiator, Method::Sas);
+
+    let commitment = a.commitment();
+
+    assert!(Pairing::opens(&commitment, &a.share(), &A));
+
+    // A man in the middle cannot swap the key or the address after committing.
+    assert!(!Pairing::opens(&commitment, &m.share(), &A));
+    assert!(!Pairing::opens(&commitment, &a.share(), &M));
+}
+
+#[test]
+fn sas_middle() {
+    // A man in the middle runs one exchange with each node, which then show different codes.
+    let a = Pairing::new([1; 32], A, Role::Initiator, Method::Sas);
+    let b = Pairing::new([2; 32], B, Role::Responder, Method::Sas);
+
+    let ma = Pairing::new([3; 32], M, Role::Responder, Method::Sas);
+    let mb = Pairing::new([3; 32], M, Role::Initiator, Method::Sas);
+
+    let ka = a.derive(M, ma.share()).unwrap();
+    let kb = b.derive(M, mb.share()).unwrap();
+
+    assert_ne!(ka.sas(), kb.sas());
+    assert_ne!(ka.link(), kb.link());
+}
@@ -0,0 +1,87 @@
+//! Byte layout of the pcapng blocks of a capture.
+
+
+
+use nrf24::{
+    capture::{
+        pcapng, Record,
+    },
+    common::{
+        AddressWidth, Channel, CRCBytes, DataRate, Gain,
+    },
+    rx::Config,
+};
+
+
+
+/// Reads a little endian word.
+fn word(buf: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes([buf[at], buf[at + 1], buf[at + 2], buf[at + 3]])
+}
+
+#[test]
+fn section() {
+    let mut buf = [0xEE; 64];
+
+    assert_eq!(pcapng::section(&mut buf[..pcapng::SECTION - 1]), None);
+    assert_eq!(pcapng::section(&mut buf), Some( pcapng::SECTION ));
+
+    assert_eq!(word(&buf, 0), 0x0A0D0D0A);
+    assert_eq!(word(&buf, 4), 28);
+    assert_eq!(&buf[8..12], &[0x4D, 0x3C, 0x2B, 0x1A]);
+    assert_eq!(&buf[12..16], &[1, 0, 0, 0]);
+    assert_eq!(&buf[16..24], &[0xFF; 8]);
+    assert_eq!(word(&buf, 24), 28);
+
+    // Nothing is written past the block.
+    assert_eq!(buf[28], 0xEE);
+}
+
+#[test]
+fn interface() {
+    let mut buf = [0xEE; 64];
+
+    assert_eq!(pcapng::interface(&mut buf), Some( pcapng::INTERFACE ));
+
+    assert_eq!(word(&buf, 0), 1);
+    assert_eq!(word(&buf, 4), 20);
+    assert_eq!(&buf[8..12], &[147, 0, 0, 0]);
+    assert_eq!(word(&buf, 12), 52);
+    assert_eq!(word(&buf, 16), 20);
+    assert_eq!(buf[20], 0xEE);
+}
+
+#[test]
+fn packet() {
+    let config = Config::configured(Channel::saturating(76), DataRate::High, Gain::Max, AddressWidth::FiveBytes, Some( CRCBytes::TwoBytes ), [None; 6]);
+
+    // 20 byte header and 5 byte payload, padded to 28 bytes.
+    let record = Record::tx([1, 2, 3, 4, 5], b"hello", true, &config, 0x0000_0001_0000_0002);
+
+    let mut buf = [0xEE; 96];
+
+    assert_eq!(pcapng::packet(&record, &mut buf[..59]), None);
+    assert_eq!(pcapng::packet(&record, &mut buf), Some( 60 ));
+
+    assert_eq!(word(&buf, 0), 6);
+    assert_eq!(word(&buf, 4), 60);
+
+    // Interface, timestamp high and low words, captured and original lengths.
+    assert_eq!(word(&buf, 8), 0);
+    assert_eq!(word(&buf, 12), 1);
+    assert_eq!(word(&buf, 16), 2);
+    assert_eq!(word(&buf, 20), 25);
+    assert_eq!(word(&buf, 24), 25);
+
+    // Link-layer header and payload.
+    assert_eq!(&buf[28..34], &[Record::VERSION, 0b11, 76, 0xFF, 0, 5]);
+    assert_eq!(&buf[34..39], &[1, 2, 3, 4, 5]);
+    assert_eq!(buf[39], 5);
+    assert_eq!(&buf[40..48], &0x0000_0001_0000_0002u64.to_le_bytes());
+    assert_eq!(&buf[48..53], b"hello");
+
+    // Padding to 32 bits and the trailing length.
+    assert_eq!(&buf[53..56], &[0; 3]);
+    assert_eq!(word(&buf, 56), 60);
+    assert_eq!(buf[60], 0xEE);
+}
@@ -0,0 +1,199 @@
+//! Replay window and authenticated encryption of the secure layer.
+
+
+
+#![cfg(feature = "secure")]
+
+
+
+use nrf24::secure::ReplayWindow;
+
+#[cfg(feature = "rustcrypto")]
+use nrf24::secure::{
+    Cipher, Link, SoftwareCcm, SoftwareChaCha,
+};
+
+
+
+/// Checks the counter and marks it as received if it is accepted.
+fn accept(window: &mut ReplayWindow, counter: u32) -> bool {
+    let new = window.check(counter);
+
+    if new {
+        window.update(counter);
+    }
+
+    new
+}
+
+#[test]
+fn window_in_order() {
+    let mut window = ReplayWindow::new();
+
+    for counter in 0..100 {
+        assert!(accept(&mut window, counter));
+    }
+}
+
+#[test]
+fn window_reordered() {
+    let mut window = ReplayWindow::new();
+
+    for counter in [5, 3, 4, 0, 1, 2, 7, 6] {
+        assert!(accept(&mut window, counter));
+    }
+
+    for counter in 0..8 {
+        assert!(!window.check(counter));
+    }
+}
+
+#[test]
+fn window_duplicate() {
+    let mut window = ReplayWindow::new();
+
+    assert!(accept(&mut window, 10));
+    assert!(!accept(&mut window, 10));
+
+    assert!(accept(&mut window, 8));
+    assert!(!accept(&mut window, 8));
+
+    assert!(accept(&mut window, 11));
+    assert!(!accept(&mut window, 10));
+    assert!(!accept(&mut window, 8));
+}
+
+#[test]
+fn window_far_behind() {
+    let mut window = ReplayWindow::new();
+
+    assert!(accept(&mut window, 100));
+
+    // Last counter inside the window.
+    assert!(accept(&mut window, 100 - (ReplayWindow::SIZE - 1)));
+
+    // First counter behind the window.
+    assert!(!window.check(100 - ReplayWindow::SIZE));
+    assert!(!window.check(0));
+}
+
+#[test]
+fn window_wrap() {
+    let mut window = ReplayWindow::new();
+
+    // A jump over the size of the window forgets the older counters.
+    assert!(accept(&mut window, 1));
+    assert!(accept(&mut window, 1 + ReplayWindow::SIZE));
+    assert!(!window.check(1));
+    assert!(accept(&mut window, 2 + ReplayWindow::SIZE));
+    assert!(!window.check(1 + ReplayWindow::SIZE));
+
+    // The highest counters do not overflow the bitmap.
+    assert!(accept(&mut window, u32::MAX - 1));
+    assert!(accept(&mut window, u32::MAX));
+    assert!(!window.check(u32::MAX));
+    assert!(!window.check(u32::MAX - 1));
+    assert!(accept(&mut window, u32::MAX - 2));
+}
+
+
+
+#[cfg(feature = "rustcrypto")]
+const KEY: [u8; 16] = [0x2B, 0x7E, 0x15, 0x16, 0x28, 0xAE, 0xD2, 0xA6, 0xAB, 0xF7, 0x15, 0x88, 0x09, 0xCF, 0x4F, 0x3C];
+
+#[cfg(feature = "rustcrypto")]
+const A: [u8; 5] = [0xA1, 0xA2, 0xA3, 0xA4, 0xA5];
+
+#[cfg(feature = "rustcrypto")]
+const B: [u8; 5] = [0xB1, 0xB2, 0xB3, 0xB4, 0xB5];
+
+#[cfg(feature = "rustcrypto")]
+#[test]
+fn ccm_round_trip() {
+    let mut cipher = SoftwareCcm::new(&KEY);
+
+    let nonce = [7; 13];
+    let mut data = *b"lights on";
+    let mut tag = [0; 8];
+
+    cipher.seal(&nonce, &mut data, &mut tag);
+    assert_ne!(&data, b"lights on");
+
+    // Tampered tag.
+    let mut copy = data;
+    let mut forged = tag;
+    forged[0] ^= 1;
+    assert!(!cipher.open(&nonce, &mut copy, &forged));
+
+    // Tampered nonce.
+    let mut copy = data;
+    let mut other = nonce;
+    other[12] ^= 1;
+    assert!(!cipher.open(&other, &mut copy, &tag));
+
+    // Tampered data.
+    let mut copy = data;
+    copy[0] ^= 1;
+    assert!(!cipher.open(&nonce, &mut copy, &tag));
+
+    assert!(cipher.open(&nonce, &mut data, &tag));
+    assert_eq!(&data, b"lights on");
+}
+
+#[cfg(feature = "rustcrypto")]
+#[test]
+fn chacha_round_trip() {
+    let mut cipher = SoftwareChaCha::new(&[0x42; 32]);
+
+    let nonce = [9; 13];
+    let mut data = *b"lights off";
+    let mut tag = [0; 16];
+
+    cipher.seal(&nonce, &mut data, &mut tag);
+
+    let mut copy = data;
+    let mut forged = tag;
+    forged[15] ^= 0x80;
+    assert!(!cipher.open(&nonce, &mut copy, &forged));
+
+    let mut copy = data;
+    let mut other = nonce;
+    other[0] ^= 1;
+    assert!(!cipher.open(&other, &mut copy, &tag));
+
+    assert!(cipher.open(&nonce, &mut data, &tag));
+    assert_eq!(&data, b"lights off");
+}
+
+#[cfg(feature = "rustcrypto")]
+#[test]
+fn link_round_trip() {
+    let mut a = Link::new(SoftwareCcm::new(&KEY), A, B);
+    let mut b = Link::new(SoftwareCcm::new(&KEY), B, A);
+
+    let mut frame = [0; 32];
+    let len = a.seal(b"dim 50", &mut frame).unwrap();
+
+    // Tampered tag.
+    let mut forged = frame;
+    forged[len - 1] ^= 1;
+    assert!(b.open(&forged[..len]).is_none());
+
+    // Tampered counter, which changes the nonce.
+    let mut forged = frame;
+    forged[0] ^= 1;
+    assert!(b.open(&forged[..len]).is_none());
+
+    // Opened as if sent by another peer, with a different nonce.
+    let mut c = Link::new(SoftwareCcm::new(&KEY), A, B);
+    assert!(c.open(&frame[..len]).is_none());
+
+    // Opened with another key.
+    let mut d = Link::new(SoftwareCcm::new(&[0; 16]), B, A);
+    assert!(d.open(&frame[..len]).is_none());
+
+    // Genuine frame, accepted once.
+    let message = b.open(&frame[..len]).unwrap();
+    assert_eq!(message.view(), b"dim 50");
+    assert!(b.open(&frame[..len]).is_none());
+}
@@ -0,0 +1,91 @@
+-- Wireshark dissector of the nrf24 capture header (LINKTYPE_USER0, 147).
+-- Install by copying into the personal plugins folder (Help > About > Folders).
+--
+-- Header, little endian, 20 bytes:
+--   0   u8     version (1)
+--   1   u8     flags: bit 0 TX, bit 1 ACK
+--   2   u8     RF channel
+--   3   u8     pipe, 0xFF if unknown or transmitted
+--   4   u8     data rate: 0 = 1 Mbps, 1 = 2 Mbps, 2 = 250 kbps
+--   5   u8     address width in bytes
+--   6   5      address, LSB first
+--   11  u8     payload length
+--   12  u64    timestamp in microseconds
+--   20  N      payload
+
+local nrf24 = Proto("nrf24", "nRF24L01 Capture")
+
+local rates = { [0] = "1 Mbps", [1] = "2 Mbps", [2] = "250 kbps" }
+
+local f = nrf24.fields
+f.version   = ProtoField.uint8("nrf24.version", "Version", base.DEC)
+f.flags     = ProtoField.uint8("nrf24.flags", "Flags", base.HEX)
+f.tx        = ProtoField.bool("nrf24.flags.tx", "TX", 8, nil, 0x01)
+f.ack       = ProtoField.bool("nrf24.flags.ack", "ACK", 8, nil, 0x02)
+f.channel   = ProtoField.uint8("nrf24.channel", "Channel", base.DEC)
+f.pipe      = ProtoField.uint8("nrf24.pipe", "Pipe", base.DEC, { [0xFF] = "None" })
+f.datarate  = ProtoField.uint8("nrf24.datarate", "Data rate", base.DEC, rates)
+f.width     = ProtoField.uint8("nrf24.width", "Address width", base.DEC)
+f.address   = ProtoField.bytes("nrf24.address", "Address", base.NONE)
+f.length    = ProtoField.uint8("nrf24.length", "Payload length", base.DEC)
+f.timestamp = ProtoField.uint64("nrf24.timestamp", "Timestamp (us)", base.DEC)
+f.payload   = ProtoField.bytes("nrf24.payload", "Payload", base.NONE)
+
+local HEADER = 20
+
+function nrf24.dissector(buffer, pinfo, tree)
+    if buffer:len() < HEADER then
+        return 0
+    end
+
+    pinfo.cols.protocol = "NRF24"
+
+    local subtree = tree:add(nrf24, buffer(0, HEADER), "nRF24L01 Capture")
+
+    subtree:add_le(f.version, buffer(0, 1))
+
+    local flags = subtree:add_le(f.flags, buffer(1, 1))
+    flags:add_le(f.tx, buffer(1, 1))
+    flags:add_le(f.ack, buffer(1, 1))
+
+    subtree:add_le(f.channel, buffer(2, 1))
+    subtree:add_le(f.pipe, buffer(3, 1))
+    subtree:add_le(f.datarate, buffer(4, 1))
+    subtree:add_le(f.width, buffer(5, 1))
+
+    -- Show the address MSB first, as it is usually written.
+    local width = math.min(buffer(5, 1):uint(), 5)
+    local bytes = buffer(6, width):bytes()
+    local address = ""
+
+    for i = width - 1, 0, -1 do
+        address = address .. string.format("%02X", bytes:get_index(i))
+    end
+
+    subtree:add(f.address, buffer(6, width)):append_text(" (" .. address .. ")")
+    subtree:add_le(f.length, buffer(11, 1))
+    subtree:add_le(f.timestamp, buffer(12, 8))
+
+    local len = math.min(buffer(11, 1):uint(), buffer:len() - HEADER)
+
+    if len > 0 then
+        subtree:add(f.payload, buffer(HEADER, len))
+    end
+
+    local tx = bit.band(buffer(1, 1):uint(), 1) ~= 0
+    local ack = bit.band(buffer(1, 1):uint(), 2) ~= 0
+
+    if tx then
+        pinfo.cols.src = "local"
+        pinfo.cols.dst = address
+    else
+        pinfo.cols.src = address
+        pinfo.cols.dst = "pipe " .. buffer(3, 1):uint()
+    end
+
+    pinfo.cols.info = string.format("%s ch %d len %d%s", tx and "TX" or "RX", buffer(2, 1):uint(), len, ack and " ACK" or "")
+
+    return buffer:len()
+end
+
+DissectorTable.get("wtap_encap"):add(wtap.USER0, nrf24)